@@ -1,33 +1,55 @@
 // Tauri commands for SmartGallery
 // Exposes all backend functionality to the frontend
 
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use sqlx::SqlitePool;
 use tauri::State;
 
 use crate::models::*;
 use crate::database;
-use crate::scanner::{ScannerConfig, full_sync};
-use crate::thumbnails::ThumbnailConfig;
+use crate::scanner::{self, ScannerConfig, ScanStats, full_sync};
+use crate::thumbnails::{self, ThumbnailConfig};
+use crate::thumbnail_queue::{self, ThumbnailQueue};
+use crate::jobs::{self, JobHandle};
+use crate::security;
 
 /// Global application state
 pub struct AppState {
     pub db_pool: Option<SqlitePool>,
+    pub db_path: Option<PathBuf>,
     pub output_path: Option<PathBuf>,
     pub input_path: Option<PathBuf>,
     pub scanner_config: Option<ScannerConfig>,
     pub thumbnail_config: Option<ThumbnailConfig>,
+    /// Background thumbnail worker pool; requests are fed via `request_thumbnails`
+    pub thumbnail_queue: Option<ThumbnailQueue>,
+    /// Handles for jobs started or resumed in this process, keyed by job id;
+    /// a handle stays here after its job completes or is cancelled.
+    /// `initialize_gallery` re-dispatches only the most recent job left
+    /// `paused`/`running` in the DB; any other such job is not in this map
+    /// and needs `start_sync_job` to be called again before it can be
+    /// paused/resumed.
+    pub active_jobs: HashMap<String, JobHandle>,
+    /// Set by `watcher::start`; `restore_snapshot` flips this before
+    /// overwriting the database file so the watcher thread stops touching
+    /// the old pool instead of racing the restore
+    pub watcher_stop: Option<Arc<std::sync::atomic::AtomicBool>>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             db_pool: None,
+            db_path: None,
             output_path: None,
             input_path: None,
             scanner_config: None,
             thumbnail_config: None,
+            thumbnail_queue: None,
+            active_jobs: HashMap::new(),
+            watcher_stop: None,
         }
     }
 }
@@ -37,6 +59,7 @@ impl AppState {
 pub async fn initialize_gallery(
     output_path: String,
     input_path: Option<String>,
+    app_handle: tauri::AppHandle,
     state: State<'_, Arc<Mutex<AppState>>>,
 ) -> Result<String, String> {
     let output_path_buf = PathBuf::from(&output_path);
@@ -57,15 +80,42 @@ pub async fn initialize_gallery(
     // Set up thumbnail config
     let thumbnail_cache_dir = output_path_buf.join("thumbnails_cache");
     let thumbnail_config = ThumbnailConfig::new(thumbnail_cache_dir);
-    
+
+    // Start the background thumbnail worker pool
+    let thumbnail_queue = ThumbnailQueue::start(
+        thumbnail_config.clone(),
+        scanner_config.animated_extensions.clone(),
+        app_handle.clone(),
+    );
+
     // Update state
-    let mut app_state = state.lock().unwrap();
-    app_state.db_pool = Some(pool);
-    app_state.output_path = Some(output_path_buf);
-    app_state.input_path = input_path.map(PathBuf::from);
-    app_state.scanner_config = Some(scanner_config);
-    app_state.thumbnail_config = Some(thumbnail_config);
-    
+    {
+        let mut app_state = state.lock().unwrap();
+        app_state.db_pool = Some(pool.clone());
+        app_state.db_path = Some(db_path);
+        app_state.output_path = Some(output_path_buf);
+        app_state.input_path = input_path.map(PathBuf::from);
+        app_state.scanner_config = Some(scanner_config.clone());
+        app_state.thumbnail_config = Some(thumbnail_config);
+        app_state.thumbnail_queue = Some(thumbnail_queue);
+    }
+
+    // Re-dispatch a sync job left running/paused when the app last closed,
+    // from its persisted checkpoint, instead of requiring the user to notice
+    // and restart it manually
+    match jobs::resume_pending(pool.clone(), scanner_config.clone(), app_handle.clone()).await {
+        Ok(Some(handle)) => {
+            state.lock().unwrap().active_jobs.insert(handle.job_id.clone(), handle);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to resume pending sync job: {}", e),
+    }
+
+    // Watch the output directory so new/changed/removed files get re-indexed
+    // live instead of waiting for the user to trigger a sync
+    let watcher_stop = crate::watcher::start(pool, scanner_config, app_handle);
+    state.lock().unwrap().watcher_stop = Some(watcher_stop);
+
     Ok("Gallery initialized successfully".to_string())
 }
 
@@ -86,8 +136,10 @@ pub async fn get_files(
     let offset = page * per_page;
     
     let files = sqlx::query(
-        "SELECT id, path, name, type, mtime, has_workflow, is_favorite, 
-                prompt_preview, sampler_names, dimensions, duration,
+        "SELECT id, path, name, type, mtime, has_workflow, is_favorite,
+                prompt_preview, sampler_names, dimensions, duration, thumbnail_path,
+                integrity_status, integrity_error, content_hash,
+                status, checked_at, file_size,
                 (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = files.id) as sampler_count
          FROM files
          ORDER BY mtime DESC
@@ -98,7 +150,7 @@ pub async fn get_files(
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Failed to fetch files: {}", e))?;
-    
+
     let file_entries: Vec<FileEntry> = files.into_iter().map(|row| FileEntry {
         id: row.get("id"),
         path: row.get("path"),
@@ -111,9 +163,16 @@ pub async fn get_files(
         sampler_names: row.get("sampler_names"),
         dimensions: row.get("dimensions"),
         duration: row.get("duration"),
+        thumbnail_path: row.get("thumbnail_path"),
+        integrity_status: row.get("integrity_status"),
+        integrity_error: row.get("integrity_error"),
+        content_hash: row.get("content_hash"),
+        status: row.get("status"),
+        checked_at: row.get("checked_at"),
+        file_size: row.get("file_size"),
         sampler_count: row.get::<i32, _>("sampler_count"),
     }).collect();
-    
+
     let total_count = database::get_file_count(pool).await? as usize;
     let has_more = (offset + file_entries.len()) < total_count;
     
@@ -137,6 +196,37 @@ pub async fn get_file_by_id(
     database::get_file_by_id(pool, &file_id).await
 }
 
+/// List all files the integrity-check pass flagged as broken, so the user can
+/// prune failed ComfyUI outputs
+#[tauri::command]
+pub async fn get_broken_files(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<FileEntry>, String> {
+    let app_state = state.lock().unwrap();
+    let pool = app_state.db_pool.as_ref()
+        .ok_or("Database not initialized")?;
+
+    database::get_broken_files(pool).await
+}
+
+/// Re-stat every indexed file and refresh its `status` (present/missing/
+/// modified), so rows for files deleted or edited outside the gallery don't
+/// keep silently looking healthy. Safe to run on a large library: it only
+/// touches the health columns, never re-extracts metadata or workflows.
+#[tauri::command]
+pub async fn reconcile_files(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<scanner::ReconcileStats, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone()
+    };
+
+    scanner::reconcile_files(&pool).await
+}
+
 /// Get workflow metadata for a file
 #[tauri::command]
 pub async fn get_workflow_metadata(
@@ -177,7 +267,9 @@ pub async fn batch_favorite(
     database::batch_set_favorite(pool, &file_ids, favorite).await
 }
 
-/// Delete a single file
+/// Delete a single file: send it to the OS recycle bin (not an unrecoverable
+/// `remove_file`) and record it in `recently_deleted` so `restore_files` can
+/// bring it back later
 #[tauri::command]
 pub async fn delete_file(
     file_id: String,
@@ -186,48 +278,167 @@ pub async fn delete_file(
     let app_state = state.lock().unwrap();
     let pool = app_state.db_pool.as_ref()
         .ok_or("Database not initialized")?;
-    
-    // Get file path before deleting from DB
+    let allowed_dirs = security::get_allowed_directories(&app_state.output_path, &app_state.input_path);
+
     let file = database::get_file_by_id(pool, &file_id).await?;
-    
+
     if let Some(file_entry) = file {
-        // Delete from filesystem
         let path = PathBuf::from(&file_entry.path);
         if path.exists() {
-            std::fs::remove_file(&path)
-                .map_err(|e| format!("Failed to delete file: {}", e))?;
+            let validated = security::validate_path(&path, &allowed_dirs)?;
+            trash::delete(&validated)
+                .map_err(|e| format!("Failed to move file to trash: {}", e))?;
+            database::record_deleted(pool, &file_id, &validated.to_string_lossy()).await?;
         }
-        
-        // Delete from database
+
         database::delete_file(pool, &file_id).await?;
     }
-    
+
     Ok(())
 }
 
-/// Delete multiple files
+/// Send multiple files to the OS recycle bin and drop their `files`/
+/// `workflow_metadata` rows. Every source path is validated against the
+/// allowed directories before any I/O. A failure on one file doesn't stop the
+/// rest — each file's outcome is reported individually instead of being
+/// silently swallowed.
 #[tauri::command]
 pub async fn batch_delete(
     file_ids: Vec<String>,
     state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<(), String> {
+) -> Result<Vec<DeleteResult>, String> {
     let app_state = state.lock().unwrap();
     let pool = app_state.db_pool.as_ref()
         .ok_or("Database not initialized")?;
-    
-    // Get file paths before deleting from DB
+    let allowed_dirs = security::get_allowed_directories(&app_state.output_path, &app_state.input_path);
+
+    let mut results = Vec::with_capacity(file_ids.len());
+    let mut deleted_ids = Vec::with_capacity(file_ids.len());
+
     for file_id in &file_ids {
-        let file = database::get_file_by_id(pool, file_id).await?;
-        if let Some(file_entry) = file {
-            let path = PathBuf::from(&file_entry.path);
-            if path.exists() {
-                let _ = std::fs::remove_file(&path); // Ignore errors, continue with DB deletion
+        match delete_one_to_trash(pool, &allowed_dirs, file_id).await {
+            Ok(()) => {
+                deleted_ids.push(file_id.clone());
+                results.push(DeleteResult { file_id: file_id.clone(), success: true, error: None });
+            }
+            Err(e) => {
+                results.push(DeleteResult { file_id: file_id.clone(), success: false, error: Some(e) });
             }
         }
     }
-    
-    // Delete from database
-    database::delete_files(pool, &file_ids).await
+
+    // Only drop DB rows for the files that actually made it to the trash
+    database::delete_files_tx(pool, &deleted_ids).await?;
+
+    Ok(results)
+}
+
+/// Move a single file to the OS trash and record it in `recently_deleted`,
+/// without touching its `files`/`workflow_metadata` rows (the caller batches
+/// that deletion across all successfully-trashed files)
+async fn delete_one_to_trash(pool: &SqlitePool, allowed_dirs: &[PathBuf], file_id: &str) -> Result<(), String> {
+    let file = database::get_file_by_id(pool, file_id).await?
+        .ok_or("File not found")?;
+
+    let path = PathBuf::from(&file.path);
+    if !path.exists() {
+        return Err("File no longer exists on disk".to_string());
+    }
+
+    let validated = security::validate_path(&path, allowed_dirs)?;
+    trash::delete(&validated)
+        .map_err(|e| format!("Failed to move file to trash: {}", e))?;
+
+    database::record_deleted(pool, file_id, &validated.to_string_lossy()).await
+}
+
+/// Recover files sent to the trash by `delete_file`/`batch_delete` back to
+/// their original location and re-insert their `files` row from a fresh scan.
+/// Per-user metadata set since the original scan (favorites, etc.) is not
+/// preserved, since the only record of it was the row we deleted.
+#[tauri::command]
+pub async fn restore_files(
+    file_ids: Vec<String>,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<DeleteResult>, String> {
+    let (pool, config) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let config = app_state.scanner_config.as_ref()
+            .ok_or("Scanner not initialized")?
+            .clone();
+        (pool, config)
+    };
+
+    let mut results = Vec::with_capacity(file_ids.len());
+
+    for file_id in &file_ids {
+        match restore_one_from_trash(&pool, &config, file_id).await {
+            Ok(()) => results.push(DeleteResult { file_id: file_id.clone(), success: true, error: None }),
+            Err(e) => results.push(DeleteResult { file_id: file_id.clone(), success: false, error: Some(e) }),
+        }
+    }
+
+    Ok(results)
+}
+
+async fn restore_one_from_trash(pool: &SqlitePool, config: &ScannerConfig, file_id: &str) -> Result<(), String> {
+    let (_, original_path) = database::get_deleted_entry(pool, file_id).await?
+        .ok_or("No trashed entry for that file")?;
+
+    let items = trash::os_limited::list()
+        .map_err(|e| format!("Failed to list trash: {}", e))?;
+    let item = items.into_iter()
+        .find(|item| item.original_parent.join(&item.name).to_string_lossy() == original_path)
+        .ok_or("File not found in trash (it may have been purged)")?;
+
+    trash::os_limited::restore_all(vec![item])
+        .map_err(|e| format!("Failed to restore file from trash: {}", e))?;
+
+    let path = PathBuf::from(&original_path);
+    scanner::process_and_store_file(pool, &path, config).await?;
+    database::remove_deleted_entry(pool, file_id).await
+}
+
+/// Permanently purge trashed files older than `older_than_days` from both the
+/// OS trash and the `recently_deleted` tracking table. Returns how many were purged.
+#[tauri::command]
+pub async fn purge_trash(
+    older_than_days: u32,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<usize, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    let stale = database::get_deleted_older_than(&pool, older_than_days).await?;
+    if stale.is_empty() {
+        return Ok(0);
+    }
+
+    let trash_items = trash::os_limited::list()
+        .map_err(|e| format!("Failed to list trash: {}", e))?;
+
+    let stale_paths: std::collections::HashSet<String> = stale.iter()
+        .map(|(_, path)| path.clone())
+        .collect();
+    let items_to_purge: Vec<_> = trash_items.into_iter()
+        .filter(|item| stale_paths.contains(&item.original_parent.join(&item.name).to_string_lossy().to_string()))
+        .collect();
+
+    if !items_to_purge.is_empty() {
+        trash::os_limited::purge_all(items_to_purge)
+            .map_err(|e| format!("Failed to purge trash: {}", e))?;
+    }
+
+    for (file_id, _) in &stale {
+        database::remove_deleted_entry(&pool, file_id).await?;
+    }
+
+    Ok(stale.len())
 }
 
 /// Sync files from disk to database
@@ -258,6 +469,135 @@ pub async fn sync_files(
     Ok(format!("Sync complete: {} files processed", stats.total_processed))
 }
 
+/// Re-index a single folder's immediate contents without touching the rest of
+/// the library. Used by the filesystem watcher and by a manual "refresh this
+/// folder" action in the UI, so a single new/changed output doesn't require a
+/// full `sync_files` pass over the whole tree.
+#[tauri::command]
+pub async fn shallow_sync(
+    folder: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<ScanStats, String> {
+    let (pool, config, allowed_dirs) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let config = app_state.scanner_config.as_ref()
+            .ok_or("Scanner not initialized")?
+            .clone();
+        let allowed_dirs = security::get_allowed_directories(&app_state.output_path, &app_state.input_path);
+        (pool, config, allowed_dirs)
+    };
+
+    let validated = security::validate_path(&PathBuf::from(&folder), &allowed_dirs)?;
+    let stats = scanner::shallow_sync(&pool, &config, &validated).await?;
+
+    let _ = app_handle.emit("fs-change", &stats);
+
+    Ok(stats)
+}
+
+/// Re-index a single output subfolder and everything beneath it, instead of
+/// the whole library — for a manual "refresh this folder" action on a
+/// subfolder that itself contains nested subfolders, where `shallow_sync`'s
+/// single-directory scope wouldn't pick up the nested changes.
+#[tauri::command]
+pub async fn sync_subpath(
+    folder: String,
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<ScanStats, String> {
+    let (pool, config, allowed_dirs) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let config = app_state.scanner_config.as_ref()
+            .ok_or("Scanner not initialized")?
+            .clone();
+        let allowed_dirs = security::get_allowed_directories(&app_state.output_path, &app_state.input_path);
+        (pool, config, allowed_dirs)
+    };
+
+    let validated = security::validate_path(&PathBuf::from(&folder), &allowed_dirs)?;
+    let stats = scanner::sync_subpath(&pool, &config, &validated).await?;
+
+    let _ = app_handle.emit("fs-change", &stats);
+
+    Ok(stats)
+}
+
+/// Start a new sync job, or resume one that was left `running`/`paused` when
+/// the app last closed, from its persisted cursor instead of rescanning
+/// everything. Multiple sync jobs can be tracked at once via their job id.
+#[tauri::command]
+pub async fn start_sync_job(
+    app_handle: tauri::AppHandle,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<String, String> {
+    let (pool, config) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let config = app_state.scanner_config.as_ref()
+            .ok_or("Scanner not initialized")?
+            .clone();
+        (pool, config)
+    };
+
+    let handle = jobs::start_or_resume(pool, config, app_handle).await?;
+    let job_id = handle.job_id.clone();
+
+    state.lock().unwrap().active_jobs.insert(job_id.clone(), handle);
+
+    Ok(job_id)
+}
+
+/// Pause a job after its current file finishes
+#[tauri::command]
+pub fn pause_job(job_id: String, state: State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
+    let app_state = state.lock().unwrap();
+    let job = app_state.active_jobs.get(&job_id).ok_or("No active job with that id")?;
+    job.pause();
+    Ok(())
+}
+
+/// Resume a paused job in place (the background task is still alive and polling)
+#[tauri::command]
+pub fn resume_job(job_id: String, state: State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
+    let app_state = state.lock().unwrap();
+    let job = app_state.active_jobs.get(&job_id).ok_or("No active job with that id")?;
+    job.resume();
+    Ok(())
+}
+
+/// Cancel a job; its progress remains in `jobs` but will not be resumed automatically
+#[tauri::command]
+pub fn cancel_job(job_id: String, state: State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
+    let app_state = state.lock().unwrap();
+    let job = app_state.active_jobs.get(&job_id).ok_or("No active job with that id")?;
+    job.cancel();
+    Ok(())
+}
+
+/// List all jobs that are queued/running/paused, whether or not they were
+/// started in this process — so the UI can offer to resume jobs left over
+/// from before the app was last closed
+#[tauri::command]
+pub async fn get_active_jobs(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<jobs::ActiveJobSummary>, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    jobs::list_active(&pool).await
+}
+
 /// Get database statistics
 #[tauri::command]
 pub async fn get_stats(
@@ -290,39 +630,194 @@ pub async fn get_stats(
     }))
 }
 
-/// Get thumbnail path for a file
+/// Get thumbnail path for a file. Scan-time processing already generates and
+/// records `thumbnail_path` (keyed by content hash, so it survives moves); this
+/// just regenerates it on demand if the cached file has gone missing.
 #[tauri::command]
 pub async fn get_thumbnail_path(
     file_id: String,
     state: State<'_, Arc<Mutex<AppState>>>,
 ) -> Result<Option<String>, String> {
-    let app_state = state.lock().unwrap();
-    let pool = app_state.db_pool.as_ref()
-        .ok_or("Database not initialized")?;
-    let thumbnail_config = app_state.thumbnail_config.as_ref()
-        .ok_or("Thumbnail config not initialized")?;
-    
-    // Get file from database
-    let file = database::get_file_by_id(pool, &file_id).await?;
-    
-    if let Some(file_entry) = file {
-        let file_path = PathBuf::from(&file_entry.path);
-        
-        // Check if thumbnail exists
-        let thumb_path = crate::thumbnails::get_thumbnail_path(&file_path, thumbnail_config);
-        
-        if let Some(path) = thumb_path {
-            return Ok(Some(path.to_string_lossy().to_string()));
+    let (pool, thumbnail_config, animated_extensions) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let thumbnail_config = app_state.thumbnail_config.as_ref()
+            .ok_or("Thumbnail config not initialized")?
+            .clone();
+        let animated_extensions = app_state.scanner_config.as_ref()
+            .map(|c| c.animated_extensions.clone())
+            .unwrap_or_default();
+        (pool, thumbnail_config, animated_extensions)
+    };
+
+    let Some(file_entry) = database::get_file_by_id(&pool, &file_id).await? else {
+        return Ok(None);
+    };
+
+    if let Some(path) = &file_entry.thumbnail_path {
+        if PathBuf::from(path).exists() {
+            return Ok(Some(path.clone()));
+        }
+    }
+
+    let file_path = PathBuf::from(&file_entry.path);
+    let content_hash = match file_entry.content_hash {
+        Some(hash) => hash,
+        None => scanner::compute_content_hash(&file_path)?,
+    };
+
+    match crate::thumbnails::generate_scan_thumbnail(
+        &file_path, &content_hash, &file_entry.file_type, file_entry.mtime,
+        &animated_extensions, &thumbnail_config,
+    ) {
+        Ok(path) => Ok(Some(path.to_string_lossy().to_string())),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Get the best cached thumbnail for `max_dimension`/`preferred_format`,
+/// generating and caching it on demand if no variant exists yet at the size
+/// tier `max_dimension` rounds up to (larger cached tiers are not reused). Lets the frontend request a size proportional to its current
+/// zoom level — a grid view asks for 256, a lightbox for 1024 — instead of
+/// always decoding the single fixed-width thumbnail `get_thumbnail_path` serves.
+///
+/// `preferred_format: "auto"` skips straight to whichever of
+/// `ThumbnailConfig::formats` encodes smallest for this image (see
+/// `thumbnails::get_or_create_auto_variant`) instead of a single named format
+/// — callers that just want "the best the client's `Accept` header allows"
+/// should resolve that to "auto" rather than guessing WebP vs AVIF themselves.
+#[tauri::command]
+pub async fn get_thumbnail_variant(
+    file_id: String,
+    max_dimension: u32,
+    preferred_format: String,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Option<String>, String> {
+    let (pool, thumbnail_config) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let thumbnail_config = app_state.thumbnail_config.as_ref()
+            .ok_or("Thumbnail config not initialized")?
+            .clone();
+        (pool, thumbnail_config)
+    };
+
+    let Some(file_entry) = database::get_file_by_id(&pool, &file_id).await? else {
+        return Ok(None);
+    };
+
+    let size = thumbnails::nearest_size_tier(max_dimension);
+    let auto = preferred_format == "auto";
+    let format = thumbnails::ThumbnailFormat::from_str(&preferred_format);
+
+    if !auto {
+        if let Some(rel_path) = database::get_thumbnail_variant(&pool, &file_id, size, format.as_str(), file_entry.mtime).await? {
+            if PathBuf::from(&rel_path).exists() {
+                return Ok(Some(rel_path));
+            }
         }
-        
-        // Try to create thumbnail
-        match crate::thumbnails::get_or_create_thumbnail(&file_path, &file_entry.file_type, thumbnail_config) {
-            Ok(path) => Ok(Some(path.to_string_lossy().to_string())),
-            Err(_) => Ok(None),
+    } else {
+        // Reuse whichever candidate format is already cached and smallest on
+        // disk, rather than re-encoding every call just because "auto" has no
+        // single cache key of its own
+        let mut cached_best: Option<(String, u64)> = None;
+        for &candidate in &thumbnail_config.formats {
+            let Some(rel_path) = database::get_thumbnail_variant(&pool, &file_id, size, candidate.as_str(), file_entry.mtime).await? else {
+                continue;
+            };
+            let Ok(meta) = std::fs::metadata(&rel_path) else { continue };
+            let bytes = meta.len();
+            if cached_best.as_ref().is_none_or(|(_, best_bytes)| bytes < *best_bytes) {
+                cached_best = Some((rel_path, bytes));
+            }
         }
+        if let Some((rel_path, _)) = cached_best {
+            return Ok(Some(rel_path));
+        }
+    }
+
+    let file_path = PathBuf::from(&file_entry.path);
+    let content_hash = match file_entry.content_hash {
+        Some(hash) => hash,
+        None => scanner::compute_content_hash(&file_path)?,
+    };
+
+    let (out_path, written_format, bytes) = if auto {
+        thumbnails::get_or_create_auto_variant(
+            &file_path, &content_hash, &file_entry.file_type, size, &thumbnail_config,
+        )?
     } else {
-        Ok(None)
+        thumbnails::get_or_create_variant(
+            &file_path, &content_hash, &file_entry.file_type, size, format, &thumbnail_config,
+        )?
+    };
+    let rel_path = out_path.to_string_lossy().to_string();
+
+    let generated_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    database::upsert_thumbnail_variant(
+        &pool, &file_id, size, written_format.as_str(), &rel_path, bytes as i64, generated_at, file_entry.mtime,
+    ).await?;
+
+    Ok(Some(rel_path))
+}
+
+/// Queue files for background thumbnail generation. `priority` should be
+/// `"high"` for files currently visible in the UI (they jump ahead of queued
+/// background work) and `"low"` for off-screen pre-generation; a repeat call
+/// for the same files re-prioritizes them in place rather than double-queuing.
+/// Completion is reported asynchronously via the `thumbnail-ready` event.
+#[tauri::command]
+pub async fn request_thumbnails(
+    file_ids: Vec<String>,
+    priority: String,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<(), String> {
+    let (pool, queue) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone();
+        let queue = app_state.thumbnail_queue.as_ref()
+            .ok_or("Thumbnail queue not initialized")?
+            .clone();
+        (pool, queue)
+    };
+
+    let priority = match priority.as_str() {
+        "high" => thumbnail_queue::Priority::High,
+        _ => thumbnail_queue::Priority::Low,
+    };
+
+    let mut files = Vec::with_capacity(file_ids.len());
+    for file_id in file_ids {
+        if let Some(file) = database::get_file_by_id(&pool, &file_id).await? {
+            files.push(file);
+        }
     }
+
+    queue.request(files, priority);
+    Ok(())
+}
+
+/// Group files that share a `content_hash` with more than one member, so the
+/// user can clean up redundant copies of the same generation
+#[tauri::command]
+pub async fn find_duplicates(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<DuplicateCluster>, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    database::find_duplicate_clusters(&pool).await
 }
 
 /// Health check
@@ -396,10 +891,45 @@ pub async fn get_filter_options(
         schedulers,
         extensions,
         prefixes: vec![],
+        statuses: vec![
+            "present".to_string(),
+            "missing".to_string(),
+            "modified".to_string(),
+            "error".to_string(),
+        ],
     })
 }
 
-/// Rename a file
+/// Given a desired destination path, return one guaranteed not to collide
+/// with an existing file. Replicates Finder-style conflict resolution: if
+/// `dir/name.ext` is taken, tries `dir/name (2).ext`, `dir/name (3).ext`, etc.
+fn unique_destination_path(dir: &Path, file_name: &std::ffi::OsStr) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let name = Path::new(file_name);
+    let stem = name.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
+    let ext = name.extension().and_then(|s| s.to_str());
+
+    let mut n = 2;
+    loop {
+        let candidate_name = match ext {
+            Some(ext) => format!("{} ({}).{}", stem, n, ext),
+            None => format!("{} ({})", stem, n),
+        };
+        let candidate = dir.join(candidate_name);
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// Rename a file. If `new_name` collides with an existing file in the same
+/// folder, auto-suffixes it Finder-style (`image (2).png`) instead of
+/// overwriting.
 #[tauri::command]
 pub async fn rename_file(
     file_id: String,
@@ -409,73 +939,113 @@ pub async fn rename_file(
     let app_state = state.lock().unwrap();
     let pool = app_state.db_pool.as_ref()
         .ok_or("Database not initialized")?;
-    
+
     // Get current file
     let file = database::get_file_by_id(pool, &file_id).await?
         .ok_or("File not found")?;
-    
+
     let old_path = PathBuf::from(&file.path);
     let parent = old_path.parent()
         .ok_or("Invalid file path")?;
-    let new_path = parent.join(&new_name);
-    
+    let new_path = unique_destination_path(parent, std::ffi::OsStr::new(&new_name));
+    let final_name = new_path.file_name()
+        .ok_or("Invalid filename")?
+        .to_string_lossy()
+        .to_string();
+
     // Rename on filesystem
     std::fs::rename(&old_path, &new_path)
         .map_err(|e| format!("Failed to rename file: {}", e))?;
-    
+
     // Update database
     sqlx::query("UPDATE files SET path = ?, name = ? WHERE id = ?")
         .bind(new_path.to_string_lossy().to_string())
-        .bind(new_name)
+        .bind(final_name)
         .bind(file_id)
         .execute(pool)
         .await
         .map_err(|e| format!("Failed to update database: {}", e))?;
-    
+
     Ok(())
 }
 
-/// Move files to a different folder
+/// Move multiple files to a different folder. Both the source of each file
+/// and the destination folder are validated against the allowed directories
+/// before any I/O. A collision with an existing file at the destination is
+/// resolved Finder-style (auto-suffixed, never overwritten). A failure moving
+/// one file doesn't stop the rest — each file's outcome is reported
+/// individually, and only the files that actually moved are committed to the
+/// database, in a single transaction.
 #[tauri::command]
 pub async fn move_files(
     file_ids: Vec<String>,
     target_folder: String,
     state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<(), String> {
+) -> Result<Vec<MoveResult>, String> {
     let app_state = state.lock().unwrap();
     let pool = app_state.db_pool.as_ref()
         .ok_or("Database not initialized")?;
-    
+    let allowed_dirs = security::get_allowed_directories(&app_state.output_path, &app_state.input_path);
+
     let target_path = PathBuf::from(&target_folder);
-    
-    // Ensure target folder exists
+
+    // Ensure target folder exists, then validate it's within the allowed dirs
     std::fs::create_dir_all(&target_path)
         .map_err(|e| format!("Failed to create target folder: {}", e))?;
-    
+    let validated_target = security::validate_path(&target_path, &allowed_dirs)?;
+
+    let mut results = Vec::with_capacity(file_ids.len());
+    let mut updates = Vec::with_capacity(file_ids.len());
+
     for file_id in file_ids {
-        // Get file
-        let file = database::get_file_by_id(pool, &file_id).await?
-            .ok_or("File not found")?;
-        
-        let old_path = PathBuf::from(&file.path);
-        let file_name = old_path.file_name()
-            .ok_or("Invalid filename")?;
-        let new_path = target_path.join(file_name);
-        
-        // Move file
-        std::fs::rename(&old_path, &new_path)
-            .map_err(|e| format!("Failed to move file: {}", e))?;
-        
-        // Update database
-        sqlx::query("UPDATE files SET path = ? WHERE id = ?")
-            .bind(new_path.to_string_lossy().to_string())
-            .bind(file_id)
-            .execute(pool)
-            .await
-            .map_err(|e| format!("Failed to update database: {}", e))?;
+        match move_one_file(pool, &allowed_dirs, &validated_target, &file_id).await {
+            Ok((new_path, new_name)) => {
+                results.push(MoveResult {
+                    file_id: file_id.clone(),
+                    success: true,
+                    new_path: Some(new_path.to_string_lossy().to_string()),
+                    error: None,
+                });
+                updates.push((file_id, new_path.to_string_lossy().to_string(), new_name));
+            }
+            Err(e) => {
+                results.push(MoveResult { file_id, success: false, new_path: None, error: Some(e) });
+            }
+        }
     }
-    
-    Ok(())
+
+    // Only commit path updates for the files that actually moved on disk
+    database::move_files_tx(pool, &updates).await?;
+
+    Ok(results)
+}
+
+/// Move a single file into `validated_target`, auto-suffixing the name on
+/// collision, without touching its `files` row (the caller batches that
+/// update across all successfully-moved files)
+async fn move_one_file(
+    pool: &SqlitePool,
+    allowed_dirs: &[PathBuf],
+    validated_target: &Path,
+    file_id: &str,
+) -> Result<(PathBuf, String), String> {
+    let file = database::get_file_by_id(pool, file_id).await?
+        .ok_or("File not found")?;
+
+    let old_path = PathBuf::from(&file.path);
+    let validated_old_path = security::validate_path(&old_path, allowed_dirs)?;
+    let file_name = validated_old_path.file_name()
+        .ok_or("Invalid filename")?;
+    let new_path = unique_destination_path(validated_target, file_name);
+    let new_name = new_path.file_name()
+        .ok_or("Invalid filename")?
+        .to_string_lossy()
+        .to_string();
+
+    std::fs::rename(&validated_old_path, &new_path)
+        .map_err(|e| format!("Failed to move file: {}", e))?;
+
+    Ok((new_path, new_name))
 }
 
 /// Search files by name or metadata
@@ -494,8 +1064,10 @@ pub async fn search_files(
     let search_pattern = format!("%{}%", query);
     
     let files = sqlx::query(
-        "SELECT id, path, name, type, mtime, has_workflow, is_favorite, 
-                prompt_preview, sampler_names, dimensions, duration,
+        "SELECT id, path, name, type, mtime, has_workflow, is_favorite,
+                prompt_preview, sampler_names, dimensions, duration, thumbnail_path,
+                integrity_status, integrity_error, content_hash,
+                status, checked_at, file_size,
                 (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = files.id) as sampler_count
          FROM files
          WHERE name LIKE ? OR prompt_preview LIKE ?
@@ -509,7 +1081,7 @@ pub async fn search_files(
     .fetch_all(pool)
     .await
     .map_err(|e| format!("Failed to search files: {}", e))?;
-    
+
     let file_entries: Vec<FileEntry> = files.into_iter().map(|row| FileEntry {
         id: row.get("id"),
         path: row.get("path"),
@@ -522,9 +1094,16 @@ pub async fn search_files(
         sampler_names: row.get("sampler_names"),
         dimensions: row.get("dimensions"),
         duration: row.get("duration"),
+        thumbnail_path: row.get("thumbnail_path"),
+        integrity_status: row.get("integrity_status"),
+        integrity_error: row.get("integrity_error"),
+        content_hash: row.get("content_hash"),
+        status: row.get("status"),
+        checked_at: row.get("checked_at"),
+        file_size: row.get("file_size"),
         sampler_count: row.get::<i32, _>("sampler_count"),
     }).collect();
-    
+
     let total_count = sqlx::query_scalar::<_, i64>(
         "SELECT COUNT(*) FROM files WHERE name LIKE ? OR prompt_preview LIKE ?"
     )
@@ -543,7 +1122,10 @@ pub async fn search_files(
     })
 }
 
-/// Get files with advanced filtering
+/// Get files with advanced filtering. Filter values are bound as query
+/// parameters (see `database::get_files_filtered`) rather than interpolated
+/// into the SQL text, and `total_count` reflects the filtered set, not the
+/// whole library, so infinite scroll's `has_more` stops at the real end.
 #[tauri::command]
 pub async fn get_files_filtered(
     filters: GalleryFilters,
@@ -551,86 +1133,16 @@ pub async fn get_files_filtered(
     per_page: usize,
     state: State<'_, Arc<Mutex<AppState>>>,
 ) -> Result<PaginatedFiles, String> {
-    let app_state = state.lock().unwrap();
-    let pool = app_state.db_pool.as_ref()
-        .ok_or("Database not initialized")?;
-    
-    let offset = page * per_page;
-    
-    // Build dynamic query based on filters
-    let mut query = String::from(
-        "SELECT DISTINCT f.id, f.path, f.name, f.type, f.mtime, f.has_workflow, f.is_favorite, 
-                f.prompt_preview, f.sampler_names, f.dimensions, f.duration,
-                (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = f.id) as sampler_count
-         FROM files f"
-    );
-    
-    let mut conditions = Vec::new();
-    
-    // Add joins if needed for workflow metadata filters
-    if filters.model.is_some() || filters.sampler.is_some() || filters.scheduler.is_some() {
-        query.push_str(" LEFT JOIN workflow_metadata wm ON f.id = wm.file_id");
-    }
-    
-    // Build WHERE conditions
-    if let Some(ref search) = filters.search {
-        if !search.is_empty() {
-            conditions.push(format!("(f.name LIKE '%{}%' OR f.prompt_preview LIKE '%{}%')", search, search));
-        }
-    }
-    
-    if filters.favorites_only {
-        conditions.push("f.is_favorite = 1".to_string());
-    }
-    
-    if let Some(ref model) = filters.model {
-        conditions.push(format!("wm.model_name = '{}'", model));
-    }
-    
-    if let Some(ref sampler) = filters.sampler {
-        conditions.push(format!("wm.sampler_name = '{}'", sampler));
-    }
-    
-    if let Some(ref scheduler) = filters.scheduler {
-        conditions.push(format!("wm.scheduler = '{}'", scheduler));
-    }
-    
-    if !conditions.is_empty() {
-        query.push_str(" WHERE ");
-        query.push_str(&conditions.join(" AND "));
-    }
-    
-    query.push_str(" ORDER BY f.mtime DESC LIMIT ? OFFSET ?");
-    
-    let files = sqlx::query(&query)
-        .bind(per_page as i64)
-        .bind(offset as i64)
-        .fetch_all(pool)
-        .await
-        .map_err(|e| format!("Failed to fetch filtered files: {}", e))?;
-    
-    let file_entries: Vec<FileEntry> = files.into_iter().map(|row| FileEntry {
-        id: row.get("id"),
-        path: row.get("path"),
-        name: row.get("name"),
-        file_type: row.get("type"),
-        mtime: row.get("mtime"),
-        has_workflow: row.get::<i32, _>("has_workflow") != 0,
-        is_favorite: row.get::<i32, _>("is_favorite") != 0,
-        prompt_preview: row.get("prompt_preview"),
-        sampler_names: row.get("sampler_names"),
-        dimensions: row.get("dimensions"),
-        duration: row.get("duration"),
-        sampler_count: row.get::<i32, _>("sampler_count"),
-    }).collect();
-    
-    // Count total matching
-    let count_query = query.replace("SELECT DISTINCT f.id,", "SELECT COUNT(DISTINCT f.id) as count FROM (SELECT f.id FROM")
-        .replace(" LIMIT ? OFFSET ?", "") + ")";
-    
-    let total_count = database::get_file_count(pool).await? as usize; // Simplified for now
-    let has_more = (offset + file_entries.len()) < total_count;
-    
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref()
+            .ok_or("Database not initialized")?
+            .clone()
+    };
+
+    let (file_entries, total_count) = database::get_files_filtered(&pool, &filters, page, per_page).await?;
+    let has_more = (page * per_page + file_entries.len()) < total_count;
+
     Ok(PaginatedFiles {
         files: file_entries,
         total_count,
@@ -663,3 +1175,168 @@ pub async fn get_config(
         "initialized": app_state.db_pool.is_some(),
     }))
 }
+
+/// Snapshots live in a `snapshots` folder next to the gallery's cache
+/// database, so they travel with the gallery's own cache directory rather
+/// than some unrelated app-data path
+fn snapshot_dir(db_path: &Path) -> Result<PathBuf, String> {
+    let cache_dir = db_path.parent().ok_or("Invalid database path")?;
+    Ok(cache_dir.join("snapshots"))
+}
+
+/// Take a consistent point-in-time copy of the gallery database, so a bad
+/// sync or corrupted index can be rolled back later with `restore_snapshot`
+#[tauri::command]
+pub async fn snapshot_database(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<SnapshotInfo, String> {
+    let (pool, db_path) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone();
+        let db_path = app_state.db_path.clone().ok_or("Database not initialized")?;
+        (pool, db_path)
+    };
+
+    database::snapshot_db(&pool, &snapshot_dir(&db_path)?).await
+}
+
+/// List previously taken database snapshots, newest first
+#[tauri::command]
+pub async fn list_snapshots(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<SnapshotInfo>, String> {
+    let db_path = {
+        let app_state = state.lock().unwrap();
+        app_state.db_path.clone().ok_or("Database not initialized")?
+    };
+
+    database::list_snapshots(&snapshot_dir(&db_path)?)
+}
+
+/// Restore the gallery database from a previously taken snapshot. The caller
+/// must re-run `initialize_gallery` afterward — this stops the filesystem
+/// watcher, closes the live connection pool (every clone held by the watcher
+/// or active jobs included, since they all share the same underlying `Pool`)
+/// and replaces the database file out from under it, so the old pool can no
+/// longer be used.
+#[tauri::command]
+pub async fn restore_snapshot(
+    filename: String,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<(), String> {
+    // `active_jobs` keeps handles of finished jobs too, so only refuse while
+    // one of them is still unfinished in the `jobs` table
+    let (check_pool, tracked_job_ids) = {
+        let app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone();
+        (pool, app_state.active_jobs.keys().cloned().collect::<Vec<_>>())
+    };
+    let unfinished = jobs::list_active(&check_pool).await?;
+    if unfinished.iter().any(|job| tracked_job_ids.contains(&job.job_id)) {
+        return Err("Cannot restore a snapshot while sync jobs are running; cancel them first".to_string());
+    }
+
+    let (pool, db_path, watcher_stop) = {
+        let mut app_state = state.lock().unwrap();
+        let pool = app_state.db_pool.take().ok_or("Database not initialized")?;
+        let db_path = app_state.db_path.clone().ok_or("Database not initialized")?;
+        let watcher_stop = app_state.watcher_stop.take();
+        (pool, db_path, watcher_stop)
+    };
+
+    // Stop the watcher before it can re-open a connection against the file
+    // we're about to replace
+    if let Some(watcher_stop) = watcher_stop {
+        watcher_stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    // Close every handle to the pool (the watcher's and any job's clones
+    // included) before touching the file on disk
+    pool.close().await;
+
+    let snapshot_path = snapshot_dir(&db_path)?.join(&filename);
+    database::restore_snapshot(&snapshot_path, &db_path)
+}
+
+/// Save the current gallery filters as a named, reusable smart collection
+#[tauri::command]
+pub async fn create_collection(
+    name: String,
+    filters: GalleryFilters,
+    per_page: usize,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Collection, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    database::create_collection(&pool, &name, &filters, per_page).await
+}
+
+/// List saved collections, each with its live `file_count`, for the sidebar
+#[tauri::command]
+pub async fn list_collections(
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<Collection>, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    database::list_collections(&pool).await
+}
+
+/// Rename a collection and/or replace its saved filters/page size
+#[tauri::command]
+pub async fn update_collection(
+    id: i64,
+    name: String,
+    filters: GalleryFilters,
+    per_page: usize,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Collection, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    database::update_collection(&pool, id, &name, &filters, per_page).await
+}
+
+/// Delete a saved collection (the files it matched are unaffected)
+#[tauri::command]
+pub async fn delete_collection(
+    id: i64,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<(), String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    database::delete_collection(&pool, id).await
+}
+
+/// Get a page of files currently matching a saved collection's filters
+#[tauri::command]
+pub async fn get_collection_files(
+    id: i64,
+    page: usize,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<PaginatedFiles, String> {
+    let pool = {
+        let app_state = state.lock().unwrap();
+        app_state.db_pool.as_ref().ok_or("Database not initialized")?.clone()
+    };
+
+    let collection = database::get_collection(&pool, id).await?;
+    let (file_entries, total_count) = database::get_collection_files(&pool, id, page).await?;
+    let has_more = (page * collection.per_page + file_entries.len()) < total_count;
+
+    Ok(PaginatedFiles {
+        files: file_entries,
+        total_count,
+        has_more,
+    })
+}
@@ -1,9 +1,10 @@
 // Database layer for SmartGallery
 // Handles SQLite connection pooling, schema initialization, and CRUD operations
 
-use sqlx::{sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions}, Row};
-use std::path::Path;
-use crate::models::{FileEntry, WorkflowMetadata};
+use sqlx::{sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow}, QueryBuilder, Row, Sqlite};
+use std::path::{Path, PathBuf};
+use crate::models::{FileEntry, GalleryFilters, WorkflowMetadata, DuplicateCluster, SnapshotInfo, Collection};
+use crate::parser::ParsedWorkflow;
 
 /// Initialize database with schema and indices
 pub async fn init_db(db_path: &Path) -> Result<SqlitePool, String> {
@@ -58,7 +59,10 @@ pub async fn init_db(db_path: &Path) -> Result<SqlitePool, String> {
             has_workflow INTEGER,
             is_favorite INTEGER DEFAULT 0,
             prompt_preview TEXT,
-            sampler_names TEXT
+            sampler_names TEXT,
+            thumbnail_path TEXT,
+            integrity_status TEXT,
+            integrity_error TEXT
         )"
     )
     .execute(&pool)
@@ -122,15 +126,314 @@ pub async fn init_db(db_path: &Path) -> Result<SqlitePool, String> {
             .map_err(|e| format!("Failed to create index: {}", e))?;
     }
 
+    // Scan cache: (path, mtime, size) -> previously extracted metadata, so an
+    // unchanged file is loaded from cache instead of re-parsed on every sync
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS scan_cache (
+            path TEXT PRIMARY KEY,
+            mtime REAL NOT NULL,
+            size INTEGER NOT NULL,
+            dimensions TEXT,
+            duration TEXT,
+            has_workflow INTEGER NOT NULL,
+            workflow_metadata TEXT NOT NULL,
+            integrity_status TEXT,
+            integrity_error TEXT,
+            content_hash TEXT
+        )"
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create scan_cache table: {}", e))?;
+
+    // Persisted, resumable background jobs (see jobs.rs). `state` is an
+    // opaque rmp-serde (msgpack) blob the job `kind` serializes its resume
+    // cursor into; `processed`/`total` are plain columns so progress can be
+    // listed cheaply without deserializing it.
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS jobs (
+            id TEXT PRIMARY KEY,
+            kind TEXT NOT NULL,
+            status TEXT NOT NULL,
+            state BLOB,
+            processed INTEGER NOT NULL DEFAULT 0,
+            total INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        )"
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create jobs table: {}", e))?;
+
+    // Deleted files, so `restore_files` can find the matching OS trash item by
+    // original path and `purge_trash` can find entries old enough to purge
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS recently_deleted (
+            file_id TEXT PRIMARY KEY,
+            original_path TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL
+        )"
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create recently_deleted table: {}", e))?;
+
+    // Cache of pre-encoded thumbnail variants at the fixed sizes/formats
+    // `thumbnails::THUMBNAIL_SIZES` produces, so the gallery can request the
+    // smallest adequate variant for its current zoom level instead of always
+    // decoding/serving one fixed-size image (see `thumbnails::get_or_create_variant`)
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS thumbnail_variants (
+            file_id TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            format TEXT NOT NULL,
+            rel_path TEXT NOT NULL,
+            bytes INTEGER NOT NULL,
+            generated_at INTEGER NOT NULL,
+            PRIMARY KEY (file_id, size, format)
+        )"
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create thumbnail_variants table: {}", e))?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_thumbnail_variants_file_id ON thumbnail_variants(file_id)")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to create index: {}", e))?;
+
+    run_migrations(&pool).await?;
+
+    // `content_hash`/`status` are added by migrations, so their indices can
+    // only be created once `run_migrations` has run
+    for index_sql in [
+        "CREATE INDEX IF NOT EXISTS idx_files_content_hash ON files(content_hash)",
+        "CREATE INDEX IF NOT EXISTS idx_files_status ON files(status)",
+    ] {
+        sqlx::query(index_sql)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to create index: {}", e))?;
+    }
+
     Ok(pool)
 }
 
+/// A single forward-only schema change, applied at most once. `version` must
+/// be unique and steps must be listed in ascending order — `run_migrations`
+/// applies them strictly in sequence so later steps can assume earlier ones
+/// already ran.
+struct Migration {
+    version: i64,
+    description: &'static str,
+    sql: &'static str,
+}
+
+/// Schema changes layered on top of the `CREATE TABLE IF NOT EXISTS` base
+/// schema above. Adding a column or backfilling data for an *existing*
+/// installation goes here as a new, higher-numbered entry — never edit or
+/// reorder a step once it has shipped, since `schema_version` already records
+/// it as applied on users' databases.
+const MIGRATIONS: &[Migration] = &[
+    // versions 1-3 and 5 (files.thumbnail_path/integrity_status/integrity_error,
+    // scan_cache.content_hash) were removed: those columns are already declared
+    // inline in the base `CREATE TABLE IF NOT EXISTS` statements above, so
+    // running them as `ALTER TABLE ADD COLUMN` against a fresh database fails
+    // with "duplicate column name". The version numbers are left unused
+    // rather than renumbered, since `run_migrations` only requires ascending,
+    // unique versions, not a contiguous range.
+    Migration { version: 4, description: "files.content_hash", sql: "ALTER TABLE files ADD COLUMN content_hash TEXT" },
+    Migration { version: 6, description: "files.status", sql: "ALTER TABLE files ADD COLUMN status TEXT NOT NULL DEFAULT 'present'" },
+    Migration { version: 7, description: "files.checked_at", sql: "ALTER TABLE files ADD COLUMN checked_at REAL" },
+    Migration { version: 8, description: "files.file_size", sql: "ALTER TABLE files ADD COLUMN file_size INTEGER" },
+    Migration { version: 9, description: "workflow_metadata.seed", sql: "ALTER TABLE workflow_metadata ADD COLUMN seed INTEGER" },
+    Migration { version: 10, description: "workflow_metadata.denoise", sql: "ALTER TABLE workflow_metadata ADD COLUMN denoise REAL" },
+    Migration { version: 11, description: "workflow_metadata.lora_names", sql: "ALTER TABLE workflow_metadata ADD COLUMN lora_names TEXT" },
+    // FTS5 prompt search: `file_search` is an external-content FTS5 index, backed
+    // by the plain `file_search_content` table (FTS5 needs an integer rowid, and
+    // `files.id` is a text hash, so the content table provides that mapping).
+    // Only the primary sampler's (sampler_index = 0) prompt is indexed, matching
+    // `prompt_preview`'s existing convention of summarizing just the first one.
+    Migration {
+        version: 12,
+        description: "file_search_content table",
+        sql: "CREATE TABLE IF NOT EXISTS file_search_content (
+            rowid INTEGER PRIMARY KEY,
+            file_id TEXT NOT NULL UNIQUE,
+            name TEXT,
+            positive_prompt TEXT,
+            negative_prompt TEXT
+        )",
+    },
+    Migration {
+        version: 13,
+        description: "file_search fts5 virtual table",
+        sql: "CREATE VIRTUAL TABLE IF NOT EXISTS file_search USING fts5(
+            name, positive_prompt, negative_prompt,
+            content='file_search_content',
+            content_rowid='rowid',
+            tokenize='porter'
+        )",
+    },
+    Migration {
+        version: 14,
+        description: "backfill file_search_content from existing files",
+        sql: "INSERT OR IGNORE INTO file_search_content (file_id, name, positive_prompt, negative_prompt)
+            SELECT f.id, f.name, wm.positive_prompt, wm.negative_prompt
+            FROM files f
+            LEFT JOIN workflow_metadata wm ON wm.file_id = f.id AND wm.sampler_index = 0",
+    },
+    Migration {
+        version: 15,
+        description: "backfill file_search from file_search_content",
+        sql: "INSERT INTO file_search(rowid, name, positive_prompt, negative_prompt)
+            SELECT rowid, name, positive_prompt, negative_prompt FROM file_search_content",
+    },
+    Migration {
+        version: 16,
+        description: "sync file_search from file_search_content inserts",
+        sql: "CREATE TRIGGER IF NOT EXISTS file_search_content_ai AFTER INSERT ON file_search_content BEGIN
+            INSERT INTO file_search(rowid, name, positive_prompt, negative_prompt)
+                VALUES (new.rowid, new.name, new.positive_prompt, new.negative_prompt);
+        END",
+    },
+    Migration {
+        version: 17,
+        description: "sync file_search from file_search_content deletes",
+        sql: "CREATE TRIGGER IF NOT EXISTS file_search_content_ad AFTER DELETE ON file_search_content BEGIN
+            INSERT INTO file_search(file_search, rowid, name, positive_prompt, negative_prompt)
+                VALUES ('delete', old.rowid, old.name, old.positive_prompt, old.negative_prompt);
+        END",
+    },
+    Migration {
+        version: 18,
+        description: "sync file_search from file_search_content updates",
+        sql: "CREATE TRIGGER IF NOT EXISTS file_search_content_au AFTER UPDATE ON file_search_content BEGIN
+            INSERT INTO file_search(file_search, rowid, name, positive_prompt, negative_prompt)
+                VALUES ('delete', old.rowid, old.name, old.positive_prompt, old.negative_prompt);
+            INSERT INTO file_search(rowid, name, positive_prompt, negative_prompt)
+                VALUES (new.rowid, new.name, new.positive_prompt, new.negative_prompt);
+        END",
+    },
+    Migration {
+        version: 19,
+        description: "populate file_search_content on new files",
+        sql: "CREATE TRIGGER IF NOT EXISTS files_search_ai AFTER INSERT ON files BEGIN
+            INSERT INTO file_search_content (file_id, name) VALUES (new.id, new.name)
+                ON CONFLICT(file_id) DO UPDATE SET name = excluded.name;
+        END",
+    },
+    Migration {
+        version: 20,
+        description: "keep file_search_content.name in sync with renames",
+        sql: "CREATE TRIGGER IF NOT EXISTS files_search_au AFTER UPDATE OF name ON files BEGIN
+            UPDATE file_search_content SET name = new.name WHERE file_id = new.id;
+        END",
+    },
+    Migration {
+        version: 21,
+        description: "drop file_search_content rows for deleted files",
+        sql: "CREATE TRIGGER IF NOT EXISTS files_search_ad AFTER DELETE ON files BEGIN
+            DELETE FROM file_search_content WHERE file_id = old.id;
+        END",
+    },
+    Migration {
+        version: 22,
+        description: "populate file_search_content prompts from the primary sampler",
+        sql: "CREATE TRIGGER IF NOT EXISTS wm_search_ai AFTER INSERT ON workflow_metadata WHEN new.sampler_index = 0 BEGIN
+            INSERT INTO file_search_content (file_id, name, positive_prompt, negative_prompt)
+                VALUES (new.file_id, (SELECT name FROM files WHERE id = new.file_id), new.positive_prompt, new.negative_prompt)
+                ON CONFLICT(file_id) DO UPDATE SET
+                    positive_prompt = excluded.positive_prompt,
+                    negative_prompt = excluded.negative_prompt;
+        END",
+    },
+    Migration {
+        version: 23,
+        description: "keep file_search_content prompts in sync with re-parsed workflows",
+        sql: "CREATE TRIGGER IF NOT EXISTS wm_search_au AFTER UPDATE ON workflow_metadata WHEN new.sampler_index = 0 BEGIN
+            UPDATE file_search_content
+                SET positive_prompt = new.positive_prompt, negative_prompt = new.negative_prompt
+                WHERE file_id = new.file_id;
+        END",
+    },
+    // Saved searches: `filters` is a `GalleryFilters` serialized to JSON, the
+    // same shape `get_files_filtered` already accepts, so a collection is
+    // re-run with the normal predicate builder rather than a parallel one
+    Migration {
+        version: 24,
+        description: "collections table",
+        sql: "CREATE TABLE IF NOT EXISTS collections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            filters TEXT NOT NULL,
+            per_page INTEGER NOT NULL DEFAULT 60,
+            created_at INTEGER NOT NULL
+        )",
+    },
+    // Lets `get_thumbnail_variant` detect a stale cache entry: the source
+    // file's mtime as of when the variant was encoded, compared against the
+    // file's current mtime on lookup so an in-place content change (which
+    // bumps mtime but may reuse the same `(file_id, size, format)` key)
+    // triggers a re-encode instead of serving the old bytes forever.
+    Migration {
+        version: 25,
+        description: "thumbnail_variants.source_mtime",
+        sql: "ALTER TABLE thumbnail_variants ADD COLUMN source_mtime REAL NOT NULL DEFAULT 0",
+    },
+];
+
+/// Apply every `MIGRATIONS` step newer than the database's `PRAGMA
+/// user_version`, each inside its own transaction, bumping the pragma as soon
+/// as a step commits. Replaces the old pattern of probing `pragma_table_info`
+/// by hand for every column this crate has ever added, and of tracking the
+/// version in an ordinary table — `user_version` is a field SQLite already
+/// reserves in the database header for exactly this, so there's no extra
+/// table to keep consistent with the rest of the schema.
+async fn run_migrations(pool: &SqlitePool) -> Result<(), String> {
+    let mut current_version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to read user_version: {}", e))?;
+
+    for migration in MIGRATIONS {
+        if migration.version <= current_version {
+            continue;
+        }
+
+        let mut tx = pool.begin()
+            .await
+            .map_err(|e| format!("Failed to start migration transaction: {}", e))?;
+
+        sqlx::query(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Migration {} ({}) failed: {}", migration.version, migration.description, e))?;
+
+        // `PRAGMA user_version = ?` doesn't accept a bound parameter, so the
+        // version is interpolated directly; it's a compile-time `i64` constant
+        // from `MIGRATIONS`, never user input.
+        sqlx::query(&format!("PRAGMA user_version = {}", migration.version))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to record user_version {}: {}", migration.version, e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit migration {}: {}", migration.version, e))?;
+
+        current_version = migration.version;
+    }
+
+    Ok(())
+}
+
 /// Insert or update a file entry
 pub async fn upsert_file(pool: &SqlitePool, file: &FileEntry) -> Result<(), String> {
     sqlx::query(
-        "INSERT OR REPLACE INTO files 
-         (id, path, name, type, mtime, has_workflow, is_favorite, prompt_preview, sampler_names, dimensions, duration)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        "INSERT OR REPLACE INTO files
+         (id, path, name, type, mtime, has_workflow, is_favorite, prompt_preview, sampler_names, dimensions, duration, thumbnail_path, integrity_status, integrity_error, content_hash, status, checked_at, file_size)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&file.id)
     .bind(&file.path)
@@ -143,18 +446,100 @@ pub async fn upsert_file(pool: &SqlitePool, file: &FileEntry) -> Result<(), Stri
     .bind(&file.sampler_names)
     .bind(&file.dimensions)
     .bind(&file.duration)
+    .bind(&file.thumbnail_path)
+    .bind(&file.integrity_status)
+    .bind(&file.integrity_error)
+    .bind(&file.content_hash)
+    .bind(&file.status)
+    .bind(file.checked_at)
+    .bind(file.file_size)
     .execute(pool)
     .await
     .map_err(|e| format!("Failed to insert file: {}", e))?;
-    
+
+    Ok(())
+}
+
+/// Upsert a batch of files and their workflow metadata inside a single
+/// transaction, amortizing WAL fsync cost across the whole batch instead of
+/// paying it per file. Used by the parallel directory walker in `scanner`,
+/// whose worker threads process files concurrently but feed a single writer
+/// that flushes every few hundred entries.
+pub async fn upsert_files_tx(
+    pool: &SqlitePool,
+    entries: &[(FileEntry, Vec<WorkflowMetadata>)],
+) -> Result<(), String> {
+    let mut tx = pool.begin().await
+        .map_err(|e| format!("Failed to start batch transaction: {}", e))?;
+
+    for (file, workflow_metadata) in entries {
+        sqlx::query(
+            "INSERT OR REPLACE INTO files
+             (id, path, name, type, mtime, has_workflow, is_favorite, prompt_preview, sampler_names, dimensions, duration, thumbnail_path, integrity_status, integrity_error, content_hash, status, checked_at, file_size)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&file.id)
+        .bind(&file.path)
+        .bind(&file.name)
+        .bind(&file.file_type)
+        .bind(file.mtime)
+        .bind(file.has_workflow as i32)
+        .bind(file.is_favorite as i32)
+        .bind(&file.prompt_preview)
+        .bind(&file.sampler_names)
+        .bind(&file.dimensions)
+        .bind(&file.duration)
+        .bind(&file.thumbnail_path)
+        .bind(&file.integrity_status)
+        .bind(&file.integrity_error)
+        .bind(&file.content_hash)
+        .bind(&file.status)
+        .bind(file.checked_at)
+        .bind(file.file_size)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to upsert file {} in batch: {}", file.id, e))?;
+
+        for meta in workflow_metadata {
+            sqlx::query(
+                "INSERT OR REPLACE INTO workflow_metadata
+                 (file_id, sampler_index, model_name, sampler_name, scheduler, cfg, steps,
+                  positive_prompt, negative_prompt, width, height, seed, denoise, lora_names)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )
+            .bind(&meta.file_id)
+            .bind(meta.sampler_index)
+            .bind(&meta.model_name)
+            .bind(&meta.sampler_name)
+            .bind(&meta.scheduler)
+            .bind(meta.cfg)
+            .bind(meta.steps)
+            .bind(&meta.positive_prompt)
+            .bind(&meta.negative_prompt)
+            .bind(meta.width)
+            .bind(meta.height)
+            .bind(meta.seed)
+            .bind(meta.denoise)
+            .bind(&meta.lora_names)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to insert workflow metadata for {} in batch: {}", meta.file_id, e))?;
+        }
+    }
+
+    tx.commit().await
+        .map_err(|e| format!("Failed to commit batch of {} files: {}", entries.len(), e))?;
+
     Ok(())
 }
 
 /// Get file by ID
 pub async fn get_file_by_id(pool: &SqlitePool, file_id: &str) -> Result<Option<FileEntry>, String> {
     let row = sqlx::query(
-        "SELECT id, path, name, type, mtime, has_workflow, is_favorite, 
-                prompt_preview, sampler_names, dimensions, duration,
+        "SELECT id, path, name, type, mtime, has_workflow, is_favorite,
+                prompt_preview, sampler_names, dimensions, duration, thumbnail_path,
+                integrity_status, integrity_error, content_hash,
+                status, checked_at, file_size,
                 (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = files.id) as sampler_count
          FROM files WHERE id = ?"
     )
@@ -176,6 +561,13 @@ pub async fn get_file_by_id(pool: &SqlitePool, file_id: &str) -> Result<Option<F
             sampler_names: row.get("sampler_names"),
             dimensions: row.get("dimensions"),
             duration: row.get("duration"),
+            thumbnail_path: row.get("thumbnail_path"),
+            integrity_status: row.get("integrity_status"),
+            integrity_error: row.get("integrity_error"),
+            content_hash: row.get("content_hash"),
+            status: row.get("status"),
+            checked_at: row.get("checked_at"),
+            file_size: row.get("file_size"),
             sampler_count: row.get::<i32, _>("sampler_count"),
         })),
         None => Ok(None),
@@ -189,28 +581,123 @@ pub async fn delete_file(pool: &SqlitePool, file_id: &str) -> Result<(), String>
         .execute(pool)
         .await
         .map_err(|e| format!("Failed to delete file: {}", e))?;
-    
+
+    Ok(())
+}
+
+/// Record that `file_id` was sent to the OS trash, so `restore_files` can find
+/// it again by original path and `purge_trash` can find it once it's old enough
+pub async fn record_deleted(pool: &SqlitePool, file_id: &str, original_path: &str) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO recently_deleted (file_id, original_path, deleted_at) VALUES (?, ?, ?)"
+    )
+    .bind(file_id)
+    .bind(original_path)
+    .bind(now_unix())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record deleted file: {}", e))?;
+
+    Ok(())
+}
+
+/// `(file_id, original_path)` of a trashed file, if it hasn't been restored or purged yet
+pub async fn get_deleted_entry(pool: &SqlitePool, file_id: &str) -> Result<Option<(String, String)>, String> {
+    let row = sqlx::query("SELECT file_id, original_path FROM recently_deleted WHERE file_id = ?")
+        .bind(file_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch deleted entry: {}", e))?;
+
+    Ok(row.map(|row| (row.get("file_id"), row.get("original_path"))))
+}
+
+/// Forget a `recently_deleted` entry after it's been restored or purged
+pub async fn remove_deleted_entry(pool: &SqlitePool, file_id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM recently_deleted WHERE file_id = ?")
+        .bind(file_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to remove deleted entry: {}", e))?;
+
     Ok(())
 }
 
-/// Delete multiple files
-pub async fn delete_files(pool: &SqlitePool, file_ids: &[String]) -> Result<(), String> {
+/// `(file_id, original_path)` of every trashed file older than `older_than_days`, for `purge_trash`
+pub async fn get_deleted_older_than(pool: &SqlitePool, older_than_days: u32) -> Result<Vec<(String, String)>, String> {
+    let cutoff = now_unix() - (older_than_days as i64) * 86400;
+
+    let rows = sqlx::query("SELECT file_id, original_path FROM recently_deleted WHERE deleted_at < ?")
+        .bind(cutoff)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch old deleted entries: {}", e))?;
+
+    Ok(rows.into_iter().map(|row| (row.get("file_id"), row.get("original_path"))).collect())
+}
+
+/// Delete multiple files' `workflow_metadata` and `files` rows as a single
+/// transaction, so a crash mid-batch can't leave orphaned metadata rows
+/// (there's no `PRAGMA foreign_keys` enforcement on the `ON DELETE CASCADE`)
+pub async fn delete_files_tx(pool: &SqlitePool, file_ids: &[String]) -> Result<(), String> {
     if file_ids.is_empty() {
         return Ok(());
     }
 
+    let mut tx = pool.begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
     let placeholders = file_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-    let query_str = format!("DELETE FROM files WHERE id IN ({})", placeholders);
-    
-    let mut query = sqlx::query(&query_str);
+
+    let mut meta_query = sqlx::query(&format!("DELETE FROM workflow_metadata WHERE file_id IN ({})", placeholders));
     for id in file_ids {
-        query = query.bind(id);
+        meta_query = meta_query.bind(id);
     }
-    
-    query.execute(pool)
+    meta_query.execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete workflow metadata: {}", e))?;
+
+    let mut files_query = sqlx::query(&format!("DELETE FROM files WHERE id IN ({})", placeholders));
+    for id in file_ids {
+        files_query = files_query.bind(id);
+    }
+    files_query.execute(&mut *tx)
         .await
         .map_err(|e| format!("Failed to delete files: {}", e))?;
-    
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(())
+}
+
+/// Update the `path` (and optionally `name`) of multiple files as a single
+/// transaction, so a move batch can't be left half-applied in the DB
+pub async fn move_files_tx(pool: &SqlitePool, updates: &[(String, String, String)]) -> Result<(), String> {
+    if updates.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for (file_id, new_path, new_name) in updates {
+        sqlx::query("UPDATE files SET path = ?, name = ? WHERE id = ?")
+            .bind(new_path)
+            .bind(new_name)
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to update path for file {}: {}", file_id, e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
     Ok(())
 }
 
@@ -260,10 +747,10 @@ pub async fn batch_set_favorite(pool: &SqlitePool, file_ids: &[String], favorite
 #[allow(dead_code)]
 pub async fn insert_workflow_metadata(pool: &SqlitePool, metadata: &WorkflowMetadata) -> Result<(), String> {
     sqlx::query(
-        "INSERT OR REPLACE INTO workflow_metadata 
-         (file_id, sampler_index, model_name, sampler_name, scheduler, cfg, steps, 
-          positive_prompt, negative_prompt, width, height)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        "INSERT OR REPLACE INTO workflow_metadata
+         (file_id, sampler_index, model_name, sampler_name, scheduler, cfg, steps,
+          positive_prompt, negative_prompt, width, height, seed, denoise, lora_names)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
     )
     .bind(&metadata.file_id)
     .bind(metadata.sampler_index)
@@ -276,18 +763,22 @@ pub async fn insert_workflow_metadata(pool: &SqlitePool, metadata: &WorkflowMeta
     .bind(&metadata.negative_prompt)
     .bind(metadata.width)
     .bind(metadata.height)
+    .bind(metadata.seed)
+    .bind(metadata.denoise)
+    .bind(&metadata.lora_names)
     .execute(pool)
     .await
     .map_err(|e| format!("Failed to insert workflow metadata: {}", e))?;
-    
+
     Ok(())
 }
 
 /// Get workflow metadata for a file
 pub async fn get_workflow_metadata(pool: &SqlitePool, file_id: &str) -> Result<Vec<WorkflowMetadata>, String> {
     let rows = sqlx::query(
-        "SELECT id, file_id, sampler_index, model_name, sampler_name, scheduler, 
-                cfg, steps, positive_prompt, negative_prompt, width, height
+        "SELECT id, file_id, sampler_index, model_name, sampler_name, scheduler,
+                cfg, steps, positive_prompt, negative_prompt, width, height,
+                seed, denoise, lora_names
          FROM workflow_metadata WHERE file_id = ? ORDER BY sampler_index"
     )
     .bind(file_id)
@@ -308,6 +799,9 @@ pub async fn get_workflow_metadata(pool: &SqlitePool, file_id: &str) -> Result<V
         negative_prompt: row.get("negative_prompt"),
         width: row.get("width"),
         height: row.get("height"),
+        seed: row.get("seed"),
+        denoise: row.get("denoise"),
+        lora_names: row.get("lora_names"),
     }).collect();
 
     Ok(metadata)
@@ -327,12 +821,1123 @@ pub async fn get_all_file_paths(pool: &SqlitePool) -> Result<Vec<(String, f64)>,
     Ok(paths)
 }
 
+/// Get `(path, mtime)` for every file whose path lives directly under `folder`
+/// (not in a subdirectory of it), for `scanner::shallow_sync`
+pub async fn get_file_paths_in_folder(pool: &SqlitePool, folder: &str) -> Result<Vec<(String, f64)>, String> {
+    let prefix = format!("{}/%", folder.trim_end_matches('/'));
+    let nested_prefix = format!("{}/%/%", folder.trim_end_matches('/'));
+
+    let rows = sqlx::query("SELECT path, mtime FROM files WHERE path LIKE ? AND path NOT LIKE ?")
+        .bind(&prefix)
+        .bind(&nested_prefix)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch file paths for folder: {}", e))?;
+
+    let paths = rows.into_iter()
+        .map(|row| (row.get("path"), row.get("mtime")))
+        .collect();
+
+    Ok(paths)
+}
+
+/// Get `(path, mtime)` for every file whose path lives under `folder`,
+/// including nested subdirectories, for `scanner::sync_subpath`
+pub async fn get_file_paths_under_prefix(pool: &SqlitePool, folder: &str) -> Result<Vec<(String, f64)>, String> {
+    let prefix = format!("{}/%", folder.trim_end_matches('/'));
+
+    let rows = sqlx::query("SELECT path, mtime FROM files WHERE path LIKE ?")
+        .bind(&prefix)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch file paths under prefix: {}", e))?;
+
+    let paths = rows.into_iter()
+        .map(|row| (row.get("path"), row.get("mtime")))
+        .collect();
+
+    Ok(paths)
+}
+
+/// Delete file rows (and their workflow metadata) by path, for files the
+/// filesystem watcher/shallow sync found missing from disk
+pub async fn delete_files_by_path(pool: &SqlitePool, paths: &[String]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let placeholders = paths.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+
+    let mut meta_query = sqlx::query(&format!(
+        "DELETE FROM workflow_metadata WHERE file_id IN (SELECT id FROM files WHERE path IN ({}))",
+        placeholders
+    ));
+    for path in paths {
+        meta_query = meta_query.bind(path);
+    }
+    meta_query.execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete workflow metadata: {}", e))?;
+
+    let mut files_query = sqlx::query(&format!("DELETE FROM files WHERE path IN ({})", placeholders));
+    for path in paths {
+        files_query = files_query.bind(path);
+    }
+    files_query.execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete files: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(())
+}
+
+/// Look up a cached thumbnail variant for `(file_id, size, format)`, for
+/// `commands::get_thumbnail_variant`. `source_mtime` is the file's current
+/// on-disk mtime; a cached row encoded against a different mtime is treated
+/// as a miss (and dropped) rather than served stale, since the source file
+/// changed in place after the variant was generated.
+pub async fn get_thumbnail_variant(
+    pool: &SqlitePool,
+    file_id: &str,
+    size: u32,
+    format: &str,
+    source_mtime: f64,
+) -> Result<Option<String>, String> {
+    let row = sqlx::query("SELECT rel_path, source_mtime FROM thumbnail_variants WHERE file_id = ? AND size = ? AND format = ?")
+        .bind(file_id)
+        .bind(size)
+        .bind(format)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch thumbnail variant: {}", e))?;
+
+    let Some(row) = row else { return Ok(None) };
+    if row.get::<f64, _>("source_mtime") != source_mtime {
+        sqlx::query("DELETE FROM thumbnail_variants WHERE file_id = ? AND size = ? AND format = ?")
+            .bind(file_id)
+            .bind(size)
+            .bind(format)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to drop stale thumbnail variant: {}", e))?;
+        return Ok(None);
+    }
+
+    Ok(Some(row.get("rel_path")))
+}
+
+/// Record a freshly-encoded thumbnail variant so the next request for the
+/// same `(file_id, size, format)` hits disk instead of re-encoding.
+/// `source_mtime` is the file's mtime at encode time, so a later lookup can
+/// tell whether the source changed since.
+pub async fn upsert_thumbnail_variant(
+    pool: &SqlitePool,
+    file_id: &str,
+    size: u32,
+    format: &str,
+    rel_path: &str,
+    bytes: i64,
+    generated_at: i64,
+    source_mtime: f64,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO thumbnail_variants (file_id, size, format, rel_path, bytes, generated_at, source_mtime)
+         VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(file_id)
+    .bind(size)
+    .bind(format)
+    .bind(rel_path)
+    .bind(bytes)
+    .bind(generated_at)
+    .bind(source_mtime)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to upsert thumbnail variant: {}", e))?;
+
+    Ok(())
+}
+
+/// Delete every cached variant for a file, so a deleted/replaced source
+/// doesn't leave orphaned thumbnail files behind
+pub async fn delete_thumbnail_variants(pool: &SqlitePool, file_id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM thumbnail_variants WHERE file_id = ?")
+        .bind(file_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete thumbnail variants: {}", e))?;
+
+    Ok(())
+}
+
 /// Get total file count
 pub async fn get_file_count(pool: &SqlitePool) -> Result<i64, String> {
     let row = sqlx::query("SELECT COUNT(*) as count FROM files")
         .fetch_one(pool)
         .await
         .map_err(|e| format!("Failed to count files: {}", e))?;
-    
+
     Ok(row.get("count"))
 }
+
+/// One row's worth of what `reconcile_files` needs to decide a file's health:
+/// its id, on-disk path, and the mtime/size it had when last indexed
+pub struct ReconcileRow {
+    pub id: String,
+    pub path: String,
+    pub mtime: f64,
+    pub file_size: Option<i64>,
+}
+
+/// Page through `(id, path, mtime, file_size)` for every file, ordered by id
+/// so repeated calls with an increasing `after_id` don't skip or repeat rows
+/// even as `reconcile_files` updates statuses in between batches
+pub async fn get_files_batch(pool: &SqlitePool, after_id: &str, limit: i64) -> Result<Vec<ReconcileRow>, String> {
+    let rows = sqlx::query(
+        "SELECT id, path, mtime, file_size FROM files WHERE id > ? ORDER BY id LIMIT ?"
+    )
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch file batch for reconcile: {}", e))?;
+
+    Ok(rows.into_iter().map(|row| ReconcileRow {
+        id: row.get("id"),
+        path: row.get("path"),
+        mtime: row.get("mtime"),
+        file_size: row.get("file_size"),
+    }).collect())
+}
+
+/// Persist the outcome of `reconcile_files` stat-ing one file
+pub async fn update_file_status(
+    pool: &SqlitePool,
+    file_id: &str,
+    status: &str,
+    checked_at: f64,
+    file_size: Option<i64>,
+) -> Result<(), String> {
+    sqlx::query("UPDATE files SET status = ?, checked_at = ?, file_size = ? WHERE id = ?")
+        .bind(status)
+        .bind(checked_at)
+        .bind(file_size)
+        .bind(file_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update file status: {}", e))?;
+
+    Ok(())
+}
+
+/// A cached scan result for a file whose `(path, mtime, size)` hasn't changed
+/// since it was last processed
+pub struct ScanCacheEntry {
+    pub dimensions: Option<String>,
+    pub duration: Option<String>,
+    pub has_workflow: bool,
+    pub workflow_metadata: Vec<ParsedWorkflow>,
+    pub integrity_status: Option<String>,
+    pub integrity_error: Option<String>,
+    /// `None` for cache rows written before the content-hash pass existed;
+    /// the caller computes it once and backfills the cache in that case
+    pub content_hash: Option<String>,
+}
+
+/// Look up a cached scan result by `(path, mtime, size)`. Returns `None` on
+/// any mismatch (including a changed size at the same mtime), which forces
+/// `process_file` to re-run.
+pub async fn get_scan_cache_entry(pool: &SqlitePool, path: &str, mtime: f64, size: i64) -> Result<Option<ScanCacheEntry>, String> {
+    let row = sqlx::query(
+        "SELECT dimensions, duration, has_workflow, workflow_metadata, integrity_status, integrity_error, content_hash
+         FROM scan_cache WHERE path = ? AND mtime = ? AND size = ?"
+    )
+    .bind(path)
+    .bind(mtime)
+    .bind(size)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch scan cache entry: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let workflow_metadata_json: String = row.get("workflow_metadata");
+    let workflow_metadata: Vec<ParsedWorkflow> = serde_json::from_str(&workflow_metadata_json)
+        .map_err(|e| format!("Failed to parse cached workflow metadata: {}", e))?;
+
+    Ok(Some(ScanCacheEntry {
+        dimensions: row.get("dimensions"),
+        duration: row.get("duration"),
+        has_workflow: row.get::<i32, _>("has_workflow") != 0,
+        workflow_metadata,
+        integrity_status: row.get("integrity_status"),
+        integrity_error: row.get("integrity_error"),
+        content_hash: row.get("content_hash"),
+    }))
+}
+
+/// Store (or replace) a scan cache entry for a freshly-processed file
+pub async fn upsert_scan_cache_entry(
+    pool: &SqlitePool,
+    path: &str,
+    mtime: f64,
+    size: i64,
+    dimensions: &Option<String>,
+    duration: &Option<String>,
+    has_workflow: bool,
+    workflow_metadata: &[ParsedWorkflow],
+    integrity_status: &Option<String>,
+    integrity_error: &Option<String>,
+    content_hash: &str,
+) -> Result<(), String> {
+    let workflow_metadata_json = serde_json::to_string(workflow_metadata)
+        .map_err(|e| format!("Failed to serialize workflow metadata: {}", e))?;
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO scan_cache (path, mtime, size, dimensions, duration, has_workflow, workflow_metadata, integrity_status, integrity_error, content_hash)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(path)
+    .bind(mtime)
+    .bind(size)
+    .bind(dimensions)
+    .bind(duration)
+    .bind(has_workflow as i32)
+    .bind(workflow_metadata_json)
+    .bind(integrity_status)
+    .bind(integrity_error)
+    .bind(content_hash)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to upsert scan cache entry: {}", e))?;
+
+    Ok(())
+}
+
+/// List all files flagged as broken by the integrity-check pass, for the
+/// `get_broken_files` command
+pub async fn get_broken_files(pool: &SqlitePool) -> Result<Vec<FileEntry>, String> {
+    let rows = sqlx::query(
+        "SELECT id, path, name, type, mtime, has_workflow, is_favorite,
+                prompt_preview, sampler_names, dimensions, duration, thumbnail_path,
+                integrity_status, integrity_error, content_hash,
+                status, checked_at, file_size,
+                (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = files.id) as sampler_count
+         FROM files WHERE integrity_status = 'broken'
+         ORDER BY mtime DESC"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch broken files: {}", e))?;
+
+    Ok(rows.into_iter().map(|row| FileEntry {
+        id: row.get("id"),
+        path: row.get("path"),
+        name: row.get("name"),
+        file_type: row.get("type"),
+        mtime: row.get("mtime"),
+        has_workflow: row.get::<i32, _>("has_workflow") != 0,
+        is_favorite: row.get::<i32, _>("is_favorite") != 0,
+        prompt_preview: row.get("prompt_preview"),
+        sampler_names: row.get("sampler_names"),
+        dimensions: row.get("dimensions"),
+        duration: row.get("duration"),
+        thumbnail_path: row.get("thumbnail_path"),
+        integrity_status: row.get("integrity_status"),
+        integrity_error: row.get("integrity_error"),
+        content_hash: row.get("content_hash"),
+        status: row.get("status"),
+        checked_at: row.get("checked_at"),
+        file_size: row.get("file_size"),
+        sampler_count: row.get::<i32, _>("sampler_count"),
+    }).collect())
+}
+
+/// Group files sharing a `content_hash` with more than one member, for the
+/// `find_duplicates` command
+pub async fn find_duplicate_clusters(pool: &SqlitePool) -> Result<Vec<DuplicateCluster>, String> {
+    let hashes: Vec<String> = sqlx::query(
+        "SELECT content_hash FROM files
+         WHERE content_hash IS NOT NULL
+         GROUP BY content_hash
+         HAVING COUNT(*) > 1"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to find duplicate content hashes: {}", e))?
+    .into_iter()
+    .map(|row| row.get("content_hash"))
+    .collect();
+
+    let mut clusters = Vec::with_capacity(hashes.len());
+    for content_hash in hashes {
+        let rows = sqlx::query(
+            "SELECT id, path, name, type, mtime, has_workflow, is_favorite,
+                    prompt_preview, sampler_names, dimensions, duration, thumbnail_path,
+                    integrity_status, integrity_error, content_hash,
+                    status, checked_at, file_size,
+                    (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = files.id) as sampler_count
+             FROM files WHERE content_hash = ?
+             ORDER BY mtime ASC"
+        )
+        .bind(&content_hash)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch duplicate cluster: {}", e))?;
+
+        let files = rows.into_iter().map(|row| FileEntry {
+            id: row.get("id"),
+            path: row.get("path"),
+            name: row.get("name"),
+            file_type: row.get("type"),
+            mtime: row.get("mtime"),
+            has_workflow: row.get::<i32, _>("has_workflow") != 0,
+            is_favorite: row.get::<i32, _>("is_favorite") != 0,
+            prompt_preview: row.get("prompt_preview"),
+            sampler_names: row.get("sampler_names"),
+            dimensions: row.get("dimensions"),
+            duration: row.get("duration"),
+            thumbnail_path: row.get("thumbnail_path"),
+            integrity_status: row.get("integrity_status"),
+            integrity_error: row.get("integrity_error"),
+            content_hash: row.get("content_hash"),
+            status: row.get("status"),
+            checked_at: row.get("checked_at"),
+            file_size: row.get("file_size"),
+            sampler_count: row.get::<i32, _>("sampler_count"),
+        }).collect();
+
+        clusters.push(DuplicateCluster { content_hash, files });
+    }
+
+    Ok(clusters)
+}
+
+/// Make a user-typed search term safe to bind into an FTS5 `MATCH` query.
+///
+/// FTS5 query syntax treats `"`, `(`, `)` as structural (phrases and grouping),
+/// so an unbalanced quote or paren — or a lone `-`/`^` prefix with nothing
+/// after it — makes SQLite return an "fts5: syntax error" instead of a result
+/// set. Rather than trying to repair the user's syntax, a malformed term is
+/// escaped and wrapped as a single literal phrase, which always matches as
+/// plain text; a well-formed term (balanced quotes/parens) is passed through
+/// unchanged so prefix/phrase/`AND`/`OR`/`NOT` searches keep working.
+fn sanitize_fts_query(term: &str) -> String {
+    let quotes_balanced = term.matches('"').count() % 2 == 0;
+    let parens_balanced = term.chars().try_fold(0i32, |depth, c| match c {
+        '(' => Some(depth + 1),
+        ')' if depth > 0 => Some(depth - 1),
+        ')' => None,
+        _ => Some(depth),
+    }) == Some(0);
+
+    if quotes_balanced && parens_balanced {
+        term.to_string()
+    } else {
+        format!("\"{}\"", term.replace('"', "\"\""))
+    }
+}
+
+/// Append `filters` as `WHERE`/`JOIN` fragments to `qb`, binding every user
+/// value through `push_bind` rather than interpolating it into the SQL text.
+/// Shared between the page query and the matching `COUNT(DISTINCT f.id)`
+/// query in `get_files_filtered` so the two can never drift out of sync.
+fn push_filter_clauses(qb: &mut QueryBuilder<'_, Sqlite>, filters: &GalleryFilters, needs_join: bool) {
+    if needs_join {
+        qb.push(" LEFT JOIN workflow_metadata wm ON f.id = wm.file_id");
+    }
+
+    let mut has_where = false;
+    macro_rules! next_clause {
+        () => {
+            if has_where {
+                qb.push(" AND ");
+            } else {
+                qb.push(" WHERE ");
+                has_where = true;
+            }
+        };
+    }
+
+    if let Some(search) = filters.search.as_ref().filter(|s| !s.is_empty()) {
+        next_clause!();
+        // FTS5 MATCH against the `file_search` index (see the migrations that
+        // create it) rather than a `LIKE` scan, so large libraries stay fast and
+        // `positive_prompt`/`negative_prompt` are searchable, not just the
+        // truncated `prompt_preview`. Supports FTS5 query syntax as-is: prefix
+        // (`word*`), quoted phrases, and `AND`/`OR`/`NOT` operators — as long
+        // as it's well-formed. `sanitize_fts_query` falls back to a literal
+        // phrase match for anything that isn't, so a stray quote or paren
+        // typed by a user searches instead of bubbling up as a SQLite error.
+        qb.push("f.id IN (SELECT fsc.file_id FROM file_search fs \
+                 JOIN file_search_content fsc ON fsc.rowid = fs.rowid \
+                 WHERE fs.file_search MATCH ").push_bind(sanitize_fts_query(search)).push(")");
+    }
+
+    if filters.favorites_only {
+        next_clause!();
+        qb.push("f.is_favorite = 1");
+    }
+
+    if let Some(has_workflow) = filters.has_workflow {
+        next_clause!();
+        qb.push("f.has_workflow = ").push_bind(if has_workflow { 1i64 } else { 0i64 });
+    }
+
+    if let Some(status) = &filters.status {
+        next_clause!();
+        qb.push("f.status = ").push_bind(status.clone());
+    }
+
+    if !filters.file_types.is_empty() {
+        next_clause!();
+        qb.push("f.type IN (");
+        let mut separated = qb.separated(", ");
+        for file_type in &filters.file_types {
+            separated.push_bind(file_type.clone());
+        }
+        qb.push(")");
+    }
+
+    if !filters.extensions.is_empty() {
+        next_clause!();
+        qb.push("(");
+        for (i, ext) in filters.extensions.iter().enumerate() {
+            if i > 0 {
+                qb.push(" OR ");
+            }
+            qb.push("LOWER(f.path) LIKE ").push_bind(format!("%{}", ext.to_lowercase()));
+        }
+        qb.push(")");
+    }
+
+    if !filters.prefixes.is_empty() {
+        next_clause!();
+        qb.push("(");
+        for (i, prefix) in filters.prefixes.iter().enumerate() {
+            if i > 0 {
+                qb.push(" OR ");
+            }
+            qb.push("f.name LIKE ").push_bind(format!("{}%", prefix));
+        }
+        qb.push(")");
+    }
+
+    if let Some(model) = &filters.model {
+        next_clause!();
+        qb.push("wm.model_name = ").push_bind(model.clone());
+    }
+    if let Some(sampler) = &filters.sampler {
+        next_clause!();
+        qb.push("wm.sampler_name = ").push_bind(sampler.clone());
+    }
+    if let Some(scheduler) = &filters.scheduler {
+        next_clause!();
+        qb.push("wm.scheduler = ").push_bind(scheduler.clone());
+    }
+    if let Some(cfg_min) = filters.cfg_min {
+        next_clause!();
+        qb.push("wm.cfg >= ").push_bind(cfg_min);
+    }
+    if let Some(cfg_max) = filters.cfg_max {
+        next_clause!();
+        qb.push("wm.cfg <= ").push_bind(cfg_max);
+    }
+    if let Some(steps_min) = filters.steps_min {
+        next_clause!();
+        qb.push("wm.steps >= ").push_bind(steps_min);
+    }
+    if let Some(steps_max) = filters.steps_max {
+        next_clause!();
+        qb.push("wm.steps <= ").push_bind(steps_max);
+    }
+    if let Some(width_min) = filters.width_min {
+        next_clause!();
+        qb.push("wm.width >= ").push_bind(width_min);
+    }
+    if let Some(width_max) = filters.width_max {
+        next_clause!();
+        qb.push("wm.width <= ").push_bind(width_max);
+    }
+    if let Some(height_min) = filters.height_min {
+        next_clause!();
+        qb.push("wm.height >= ").push_bind(height_min);
+    }
+    if let Some(height_max) = filters.height_max {
+        next_clause!();
+        qb.push("wm.height <= ").push_bind(height_max);
+    }
+    if let Some(duration_min) = filters.duration_min {
+        next_clause!();
+        qb.push("CAST(f.duration AS REAL) >= ").push_bind(duration_min);
+    }
+    if let Some(duration_max) = filters.duration_max {
+        next_clause!();
+        qb.push("CAST(f.duration AS REAL) <= ").push_bind(duration_max);
+    }
+    if let Some(date_from) = filters.date_from.as_ref().filter(|s| !s.is_empty()) {
+        next_clause!();
+        qb.push("datetime(f.mtime, 'unixepoch') >= datetime(").push_bind(date_from.clone()).push(")");
+    }
+    if let Some(date_to) = filters.date_to.as_ref().filter(|s| !s.is_empty()) {
+        next_clause!();
+        qb.push("datetime(f.mtime, 'unixepoch') <= datetime(").push_bind(date_to.clone()).push(")");
+    }
+}
+
+/// Whether `filters` references any `workflow_metadata` column, so the
+/// page query and the count query in `get_files_filtered`/`count_files_filtered`
+/// always agree on whether to `LEFT JOIN` it
+fn filters_need_workflow_join(filters: &GalleryFilters) -> bool {
+    filters.model.is_some()
+        || filters.sampler.is_some()
+        || filters.scheduler.is_some()
+        || filters.cfg_min.is_some()
+        || filters.cfg_max.is_some()
+        || filters.steps_min.is_some()
+        || filters.steps_max.is_some()
+        || filters.width_min.is_some()
+        || filters.width_max.is_some()
+        || filters.height_min.is_some()
+        || filters.height_max.is_some()
+}
+
+/// Get a page of files matching `filters`, plus the exact total matching
+/// count (via a matching `COUNT(DISTINCT f.id)` query) so `has_more` reflects
+/// the real end of the filtered set rather than the whole library.
+pub async fn get_files_filtered(
+    pool: &SqlitePool,
+    filters: &GalleryFilters,
+    page: usize,
+    per_page: usize,
+) -> Result<(Vec<FileEntry>, usize), String> {
+    let needs_join = filters_need_workflow_join(filters);
+
+    let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT DISTINCT f.id, f.path, f.name, f.type, f.mtime, f.has_workflow, f.is_favorite,
+                f.prompt_preview, f.sampler_names, f.dimensions, f.duration, f.thumbnail_path,
+                f.integrity_status, f.integrity_error, f.content_hash,
+                f.status, f.checked_at, f.file_size,
+                (SELECT COUNT(*) FROM workflow_metadata WHERE file_id = f.id) as sampler_count
+         FROM files f"
+    );
+    push_filter_clauses(&mut qb, filters, needs_join);
+
+    // "relevance" only means anything alongside an active search; otherwise
+    // fall back to the normal newest-first ordering
+    let search_active = filters.search.as_ref().is_some_and(|s| !s.is_empty());
+    if search_active && filters.sort_by.as_deref() == Some("relevance") {
+        qb.push(" ORDER BY (SELECT bm25(file_search) FROM file_search fs \
+                  JOIN file_search_content fsc ON fsc.rowid = fs.rowid \
+                  WHERE fsc.file_id = f.id) ASC");
+    } else {
+        qb.push(" ORDER BY f.mtime DESC");
+    }
+    qb.push(" LIMIT ").push_bind(per_page as i64)
+      .push(" OFFSET ").push_bind((page * per_page) as i64);
+
+    let rows = qb.build()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch filtered files: {}", e))?;
+
+    let file_entries: Vec<FileEntry> = rows.into_iter().map(|row| FileEntry {
+        id: row.get("id"),
+        path: row.get("path"),
+        name: row.get("name"),
+        file_type: row.get("type"),
+        mtime: row.get("mtime"),
+        has_workflow: row.get::<i32, _>("has_workflow") != 0,
+        is_favorite: row.get::<i32, _>("is_favorite") != 0,
+        prompt_preview: row.get("prompt_preview"),
+        sampler_names: row.get("sampler_names"),
+        dimensions: row.get("dimensions"),
+        duration: row.get("duration"),
+        thumbnail_path: row.get("thumbnail_path"),
+        integrity_status: row.get("integrity_status"),
+        integrity_error: row.get("integrity_error"),
+        content_hash: row.get("content_hash"),
+        status: row.get("status"),
+        checked_at: row.get("checked_at"),
+        file_size: row.get("file_size"),
+        sampler_count: row.get::<i32, _>("sampler_count"),
+    }).collect();
+
+    let total_count = count_files_filtered(pool, filters).await?;
+
+    Ok((file_entries, total_count))
+}
+
+/// Count files matching `filters` without fetching a page of rows. Shares
+/// `push_filter_clauses` with `get_files_filtered`'s page query so the two
+/// can never drift out of sync, and is reused by `collections` to keep each
+/// saved search's `file_count` rollup accurate
+pub async fn count_files_filtered(pool: &SqlitePool, filters: &GalleryFilters) -> Result<usize, String> {
+    let needs_join = filters_need_workflow_join(filters);
+
+    let mut count_qb: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT COUNT(DISTINCT f.id) FROM files f"
+    );
+    push_filter_clauses(&mut count_qb, filters, needs_join);
+
+    let total_count: i64 = count_qb.build_query_scalar()
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to count filtered files: {}", e))?;
+
+    Ok(total_count as usize)
+}
+
+/// A persisted job row, as needed to resume it after an app crash/close.
+/// `state` is the job kind's own opaque rmp-serde cursor blob
+pub struct JobRecord {
+    pub id: String,
+    pub kind: String,
+    pub status: String,
+    pub state: Vec<u8>,
+    pub processed: usize,
+    pub total: usize,
+}
+
+fn now_unix() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Create and persist a new job in `running` status with its initial cursor state
+pub async fn create_job(pool: &SqlitePool, job_id: &str, kind: &str, state: &[u8], total: usize) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO jobs (id, kind, status, state, processed, total, updated_at)
+         VALUES (?, ?, 'running', ?, 0, ?, ?)"
+    )
+    .bind(job_id)
+    .bind(kind)
+    .bind(state)
+    .bind(total as i64)
+    .bind(now_unix())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create job: {}", e))?;
+
+    Ok(())
+}
+
+/// Fetch jobs of a given `kind` left `running` or `paused` (e.g. the app was
+/// closed mid-scan), most recently updated first, so they can be resumed
+/// instead of starting over
+pub async fn get_resumable_jobs(pool: &SqlitePool, kind: &str) -> Result<Vec<JobRecord>, String> {
+    let rows = sqlx::query(
+        "SELECT id, kind, status, state, processed, total FROM jobs
+         WHERE kind = ? AND status IN ('running', 'paused')
+         ORDER BY updated_at DESC"
+    )
+    .bind(kind)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch resumable jobs: {}", e))?;
+
+    Ok(rows.into_iter().map(row_to_job_record).collect())
+}
+
+/// List all jobs not yet finished (queued/running/paused), for the
+/// `get_active_jobs` command
+pub async fn get_active_jobs(pool: &SqlitePool) -> Result<Vec<JobRecord>, String> {
+    let rows = sqlx::query(
+        "SELECT id, kind, status, state, processed, total FROM jobs
+         WHERE status IN ('queued', 'running', 'paused')
+         ORDER BY updated_at DESC"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch active jobs: {}", e))?;
+
+    Ok(rows.into_iter().map(row_to_job_record).collect())
+}
+
+fn row_to_job_record(row: sqlx::sqlite::SqliteRow) -> JobRecord {
+    JobRecord {
+        id: row.get("id"),
+        kind: row.get("kind"),
+        status: row.get("status"),
+        state: row.get("state"),
+        processed: row.get::<i64, _>("processed") as usize,
+        total: row.get::<i64, _>("total") as usize,
+    }
+}
+
+/// Persist a job's cursor state blob and processed count (called every few
+/// files/seconds from the job's run loop, not on every single file)
+pub async fn update_job_state(pool: &SqlitePool, job_id: &str, state: &[u8], processed: usize) -> Result<(), String> {
+    sqlx::query("UPDATE jobs SET state = ?, processed = ?, updated_at = ? WHERE id = ?")
+        .bind(state)
+        .bind(processed as i64)
+        .bind(now_unix())
+        .bind(job_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update job state: {}", e))?;
+
+    Ok(())
+}
+
+/// Cheaply bump a job's `processed` counter without touching its (larger)
+/// cursor blob; used between checkpoints so progress still looks live
+pub async fn update_job_progress(pool: &SqlitePool, job_id: &str, processed: usize) -> Result<(), String> {
+    sqlx::query("UPDATE jobs SET processed = ?, updated_at = ? WHERE id = ?")
+        .bind(processed as i64)
+        .bind(now_unix())
+        .bind(job_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update job progress: {}", e))?;
+
+    Ok(())
+}
+
+/// Update a job's status (queued/running/paused/completed/failed)
+pub async fn set_job_status(pool: &SqlitePool, job_id: &str, status: &str) -> Result<(), String> {
+    sqlx::query("UPDATE jobs SET status = ?, updated_at = ? WHERE id = ?")
+        .bind(status)
+        .bind(now_unix())
+        .bind(job_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update job status: {}", e))?;
+
+    Ok(())
+}
+
+/// Take a consistent point-in-time copy of the database into `snapshot_dir`,
+/// named `gallery_snapshot_<unix_seconds>.sqlite`. Uses `VACUUM INTO`,
+/// SQLite's online backup mechanism, so it doesn't block concurrent readers/
+/// writers the way copying the file by hand would with WAL mode enabled.
+pub async fn snapshot_db(pool: &SqlitePool, snapshot_dir: &Path) -> Result<SnapshotInfo, String> {
+    std::fs::create_dir_all(snapshot_dir)
+        .map_err(|e| format!("Failed to create snapshot directory: {}", e))?;
+
+    let created_at = now_unix();
+    let filename = format!("gallery_snapshot_{}.sqlite", created_at);
+    let snapshot_path = snapshot_dir.join(&filename);
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to snapshot database: {}", e))?;
+
+    let size_bytes = std::fs::metadata(&snapshot_path)
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    Ok(SnapshotInfo {
+        filename,
+        path: snapshot_path.to_string_lossy().to_string(),
+        created_at,
+        size_bytes,
+    })
+}
+
+/// List snapshots previously written by `snapshot_db`, newest first
+pub fn list_snapshots(snapshot_dir: &Path) -> Result<Vec<SnapshotInfo>, String> {
+    if !snapshot_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(snapshot_dir)
+        .map_err(|e| format!("Failed to read snapshot directory: {}", e))?
+    {
+        let entry = entry.map_err(|e| format!("Failed to read snapshot entry: {}", e))?;
+        let path = entry.path();
+        let Some(filename) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        if !filename.starts_with("gallery_snapshot_") || !filename.ends_with(".sqlite") {
+            continue;
+        }
+
+        let created_at = filename
+            .trim_start_matches("gallery_snapshot_")
+            .trim_end_matches(".sqlite")
+            .parse::<i64>()
+            .unwrap_or(0);
+        let size_bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+
+        snapshots.push(SnapshotInfo {
+            filename: filename.to_string(),
+            path: path.to_string_lossy().to_string(),
+            created_at,
+            size_bytes,
+        });
+    }
+
+    snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(snapshots)
+}
+
+/// Restore a snapshot over the live database file. The caller must drop the
+/// existing connection pool before calling this and reinitialize it (via
+/// `init_db`) afterward — replacing a SQLite file on disk isn't safe while a
+/// pool still holds the old one open.
+pub fn restore_snapshot(snapshot_path: &Path, db_path: &Path) -> Result<(), String> {
+    if !snapshot_path.exists() {
+        return Err(format!("Snapshot not found: {}", snapshot_path.display()));
+    }
+
+    std::fs::copy(snapshot_path, db_path)
+        .map_err(|e| format!("Failed to restore snapshot: {}", e))?;
+
+    // Drop any stale WAL/SHM files left over from the database being
+    // replaced; the restored file is self-consistent and doesn't need them
+    for ext in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(PathBuf::from(format!("{}{}", db_path.display(), ext)));
+    }
+
+    Ok(())
+}
+
+/// Deserialize a `collections` row, attaching its live `file_count` via
+/// `count_files_filtered` rather than trusting a stored number that could
+/// drift as the library changes underneath it
+async fn row_to_collection(pool: &SqlitePool, row: SqliteRow) -> Result<Collection, String> {
+    let filters_json: String = row.get("filters");
+    let filters: GalleryFilters = serde_json::from_str(&filters_json)
+        .map_err(|e| format!("Failed to parse collection filters: {}", e))?;
+    let file_count = count_files_filtered(pool, &filters).await?;
+
+    Ok(Collection {
+        id: row.get("id"),
+        name: row.get("name"),
+        filters,
+        per_page: row.get::<i64, _>("per_page") as usize,
+        created_at: row.get("created_at"),
+        file_count,
+    })
+}
+
+/// Save a new smart collection: a name plus the `GalleryFilters` it re-runs
+/// on every view, so the user doesn't have to rebuild the same multi-field
+/// filter from scratch each time
+pub async fn create_collection(pool: &SqlitePool, name: &str, filters: &GalleryFilters, per_page: usize) -> Result<Collection, String> {
+    let filters_json = serde_json::to_string(filters)
+        .map_err(|e| format!("Failed to serialize collection filters: {}", e))?;
+    let created_at = now_unix();
+
+    let id: i64 = sqlx::query(
+        "INSERT INTO collections (name, filters, per_page, created_at) VALUES (?, ?, ?, ?)"
+    )
+    .bind(name)
+    .bind(&filters_json)
+    .bind(per_page as i64)
+    .bind(created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create collection: {}", e))?
+    .last_insert_rowid();
+
+    get_collection(pool, id).await
+}
+
+/// List saved collections, newest first, each with its current `file_count`
+pub async fn list_collections(pool: &SqlitePool) -> Result<Vec<Collection>, String> {
+    let rows = sqlx::query(
+        "SELECT id, name, filters, per_page, created_at FROM collections ORDER BY created_at DESC"
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch collections: {}", e))?;
+
+    let mut collections = Vec::with_capacity(rows.len());
+    for row in rows {
+        collections.push(row_to_collection(pool, row).await?);
+    }
+    Ok(collections)
+}
+
+/// Fetch a single collection by id
+pub async fn get_collection(pool: &SqlitePool, id: i64) -> Result<Collection, String> {
+    let row = sqlx::query(
+        "SELECT id, name, filters, per_page, created_at FROM collections WHERE id = ?"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch collection: {}", e))?
+    .ok_or_else(|| format!("Collection {} not found", id))?;
+
+    row_to_collection(pool, row).await
+}
+
+/// Rename a collection and/or replace its saved filters/page size
+pub async fn update_collection(pool: &SqlitePool, id: i64, name: &str, filters: &GalleryFilters, per_page: usize) -> Result<Collection, String> {
+    let filters_json = serde_json::to_string(filters)
+        .map_err(|e| format!("Failed to serialize collection filters: {}", e))?;
+
+    let result = sqlx::query(
+        "UPDATE collections SET name = ?, filters = ?, per_page = ? WHERE id = ?"
+    )
+    .bind(name)
+    .bind(&filters_json)
+    .bind(per_page as i64)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update collection: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Collection {} not found", id));
+    }
+
+    get_collection(pool, id).await
+}
+
+/// Delete a saved collection. The files it matched are untouched — only the
+/// saved search itself is removed
+pub async fn delete_collection(pool: &SqlitePool, id: i64) -> Result<(), String> {
+    let result = sqlx::query("DELETE FROM collections WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete collection: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err(format!("Collection {} not found", id));
+    }
+
+    Ok(())
+}
+
+/// Materialize a collection's current matching files — its saved filters run
+/// through the same `get_files_filtered` query every other gallery view uses,
+/// so a collection always reflects the library as it is now, not as it was
+/// when saved
+pub async fn get_collection_files(pool: &SqlitePool, id: i64, page: usize) -> Result<(Vec<FileEntry>, usize), String> {
+    let collection = get_collection(pool, id).await?;
+    get_files_filtered(pool, &collection.filters, page, collection.per_page).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `GalleryFilters` with every field at its "no filter applied" value,
+    /// so each test only needs to set the one or two fields it's checking
+    fn empty_filters() -> GalleryFilters {
+        GalleryFilters {
+            search: None,
+            folder_key: None,
+            favorites_only: false,
+            file_types: vec![],
+            extensions: vec![],
+            prefixes: vec![],
+            model: None,
+            sampler: None,
+            scheduler: None,
+            cfg_min: None,
+            cfg_max: None,
+            steps_min: None,
+            steps_max: None,
+            width_min: None,
+            width_max: None,
+            height_min: None,
+            height_max: None,
+            duration_min: None,
+            duration_max: None,
+            has_workflow: None,
+            date_from: None,
+            date_to: None,
+            status: None,
+            sort_by: None,
+        }
+    }
+
+    #[test]
+    fn no_filters_adds_no_where_or_join() {
+        let filters = empty_filters();
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM files f");
+        push_filter_clauses(&mut qb, &filters, filters_need_workflow_join(&filters));
+
+        assert_eq!(qb.sql(), "SELECT * FROM files f");
+    }
+
+    #[test]
+    fn favorites_only_filters_on_is_favorite() {
+        let mut filters = empty_filters();
+        filters.favorites_only = true;
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM files f");
+        push_filter_clauses(&mut qb, &filters, filters_need_workflow_join(&filters));
+
+        assert_eq!(qb.sql(), "SELECT * FROM files f WHERE f.is_favorite = 1");
+    }
+
+    #[test]
+    fn file_types_binds_one_placeholder_per_value() {
+        let mut filters = empty_filters();
+        filters.file_types = vec!["image".to_string(), "video".to_string()];
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM files f");
+        push_filter_clauses(&mut qb, &filters, filters_need_workflow_join(&filters));
+
+        assert_eq!(qb.sql(), "SELECT * FROM files f WHERE f.type IN (?, ?)");
+    }
+
+    #[test]
+    fn multiple_filters_are_joined_with_and() {
+        let mut filters = empty_filters();
+        filters.favorites_only = true;
+        filters.has_workflow = Some(true);
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM files f");
+        push_filter_clauses(&mut qb, &filters, filters_need_workflow_join(&filters));
+
+        assert_eq!(
+            qb.sql(),
+            "SELECT * FROM files f WHERE f.is_favorite = 1 AND f.has_workflow = ?"
+        );
+    }
+
+    #[test]
+    fn model_filter_requires_the_workflow_join() {
+        let mut filters = empty_filters();
+        filters.model = Some("sd_xl".to_string());
+        assert!(filters_need_workflow_join(&filters));
+
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT * FROM files f");
+        push_filter_clauses(&mut qb, &filters, filters_need_workflow_join(&filters));
+
+        assert_eq!(
+            qb.sql(),
+            "SELECT * FROM files f LEFT JOIN workflow_metadata wm ON f.id = wm.file_id WHERE wm.model_name = ?"
+        );
+    }
+
+    #[test]
+    fn favorites_and_date_filters_do_not_require_the_workflow_join() {
+        let mut filters = empty_filters();
+        filters.favorites_only = true;
+        filters.date_from = Some("2026-01-01".to_string());
+        assert!(!filters_need_workflow_join(&filters));
+    }
+
+    #[test]
+    fn sanitize_fts_query_passes_through_well_formed_syntax() {
+        assert_eq!(sanitize_fts_query("cat AND dog"), "cat AND dog");
+        assert_eq!(sanitize_fts_query("\"a phrase\""), "\"a phrase\"");
+        assert_eq!(sanitize_fts_query("(cat OR dog)"), "(cat OR dog)");
+        assert_eq!(sanitize_fts_query("word*"), "word*");
+    }
+
+    #[test]
+    fn sanitize_fts_query_quotes_unbalanced_input() {
+        assert_eq!(sanitize_fts_query("\"unterminated"), "\"\"\"unterminated\"");
+        assert_eq!(sanitize_fts_query("(unclosed"), "\"(unclosed\"");
+        assert_eq!(sanitize_fts_query("extra)"), "\"extra)\"");
+    }
+}
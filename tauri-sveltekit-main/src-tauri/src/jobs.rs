@@ -0,0 +1,334 @@
+// Persisted, resumable background jobs for SmartGallery
+//
+// `full_sync` in scanner.rs is a single blocking pass with no way to pause,
+// cancel, or resume a large scan. This module drives the same per-file work
+// sequentially on a background task, checking a cancellation/pause flag
+// between files and checkpointing its resume cursor to the generic `jobs`
+// table (see database.rs) so an interrupted scan (app crash/close) can pick
+// up where it left off instead of rescanning from zero.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::Emitter;
+
+use crate::database;
+use crate::models::SyncProgress;
+use crate::scanner::{self, ScanStats, ScannerConfig};
+
+/// The only job `kind` today; the `jobs` table carries a `kind` column so
+/// other background job types (thumbnail backfills, etc.) can share it later
+const JOB_KIND_SYNC: &str = "sync";
+
+/// Checkpoint the resume cursor every this many files processed...
+const CHECKPOINT_EVERY_FILES: usize = 10;
+/// ...or this often in wall-clock time, whichever comes first
+const CHECKPOINT_EVERY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Cancelled,
+    Completed,
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Paused => "paused",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+/// The sync job's resumable cursor: the full work-list plus how far into it
+/// we've gotten, and the running stats accumulated so far. Persisted to the
+/// `jobs.state` blob via `rmp-serde` (msgpack).
+///
+/// `root_path` records the `output_path` the work list was built against, so
+/// a job left `running`/`paused` from a previous gallery folder isn't silently
+/// resumed against a since-changed one (its file indices would point at paths
+/// that may no longer exist, or belong to a different library entirely).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncCursor {
+    root_path: String,
+    work_list: Vec<String>,
+    completed: usize,
+    stats: ScanStats,
+}
+
+/// Handle to a running (or paused) job, shared between the Tauri command
+/// layer and the background task that's actually driving the scan
+#[derive(Clone)]
+pub struct JobHandle {
+    pub job_id: String,
+    cancelled: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    fn new(job_id: String) -> Self {
+        Self {
+            job_id,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            paused: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatusReport {
+    pub job_id: String,
+    pub status: String,
+    pub total: usize,
+    pub completed: usize,
+}
+
+/// Summary row for the `get_active_jobs` command
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveJobSummary {
+    pub job_id: String,
+    pub kind: String,
+    pub status: String,
+    pub processed: usize,
+    pub total: usize,
+}
+
+fn new_job_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("job_{}", nanos)
+}
+
+/// List jobs that are queued/running/paused, for the `get_active_jobs` command
+pub async fn list_active(pool: &SqlitePool) -> Result<Vec<ActiveJobSummary>, String> {
+    let jobs = database::get_active_jobs(pool).await?;
+    Ok(jobs.into_iter().map(|job| ActiveJobSummary {
+        job_id: job.id,
+        kind: job.kind,
+        status: job.status,
+        processed: job.processed,
+        total: job.total,
+    }).collect())
+}
+
+/// Start a new sync job, or resume one that was left `running`/`paused` when
+/// the app last closed. Work is driven on a background task; the returned
+/// handle lets the command layer pause/cancel it without blocking on it.
+pub async fn start_or_resume(
+    pool: SqlitePool,
+    config: ScannerConfig,
+    app_handle: tauri::AppHandle,
+) -> Result<JobHandle, String> {
+    let resumable = database::get_resumable_jobs(&pool, JOB_KIND_SYNC).await?
+        .into_iter()
+        .next();
+
+    let root_path = config.output_path.to_string_lossy().to_string();
+
+    let decoded = match resumable {
+        Some(job) => {
+            let cursor: SyncCursor = rmp_serde::from_slice(&job.state)
+                .map_err(|e| format!("Failed to decode job cursor: {}", e))?;
+            if cursor.root_path == root_path {
+                Some((job.id, cursor))
+            } else {
+                // The gallery now points somewhere else; the old work list is
+                // meaningless here, so abandon it instead of resuming blind
+                database::set_job_status(&pool, &job.id, JobStatus::Cancelled.as_str()).await?;
+                None
+            }
+        }
+        None => None,
+    };
+
+    let (job_id, cursor) = match decoded {
+        Some(pair) => pair,
+        None => {
+            let work_list = scanner::compute_work_list(&pool, &config).await?;
+            let cursor = SyncCursor { root_path, work_list, completed: 0, stats: ScanStats::new() };
+            let job_id = new_job_id();
+            let state = rmp_serde::to_vec(&cursor)
+                .map_err(|e| format!("Failed to encode job cursor: {}", e))?;
+            database::create_job(&pool, &job_id, JOB_KIND_SYNC, &state, cursor.work_list.len()).await?;
+            (job_id, cursor)
+        }
+    };
+
+    database::set_job_status(&pool, &job_id, JobStatus::Running.as_str()).await?;
+    Ok(dispatch(pool, config, job_id, cursor, app_handle))
+}
+
+/// Re-dispatch a sync job left `running`/`paused` when the app was last
+/// closed, from its last persisted checkpoint. Called once at gallery
+/// initialization; returns `None` (and starts nothing) if no such job exists,
+/// so a fresh gallery doesn't kick off a scan nobody asked for.
+pub async fn resume_pending(
+    pool: SqlitePool,
+    config: ScannerConfig,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<JobHandle>, String> {
+    let Some(job) = database::get_resumable_jobs(&pool, JOB_KIND_SYNC).await?.into_iter().next() else {
+        return Ok(None);
+    };
+
+    let cursor: SyncCursor = rmp_serde::from_slice(&job.state)
+        .map_err(|e| format!("Failed to decode job cursor: {}", e))?;
+
+    let root_path = config.output_path.to_string_lossy().to_string();
+    if cursor.root_path != root_path {
+        // Stale job from a previously-configured gallery folder; nothing to
+        // resume against the current one
+        database::set_job_status(&pool, &job.id, JobStatus::Cancelled.as_str()).await?;
+        return Ok(None);
+    }
+
+    database::set_job_status(&pool, &job.id, JobStatus::Running.as_str()).await?;
+    Ok(Some(dispatch(pool, config, job.id, cursor, app_handle)))
+}
+
+/// Spawn the background task driving a job from its cursor and return a
+/// handle the command layer can pause/cancel without blocking on it
+fn dispatch(
+    pool: SqlitePool,
+    config: ScannerConfig,
+    job_id: String,
+    cursor: SyncCursor,
+    app_handle: tauri::AppHandle,
+) -> JobHandle {
+    let handle = JobHandle::new(job_id);
+    let task_handle = handle.clone();
+
+    tauri::async_runtime::spawn(run_job(pool, config, task_handle, cursor, app_handle));
+
+    handle
+}
+
+/// Persist the job's full resume cursor (work list + index + stats) as a
+/// msgpack blob. Called every `CHECKPOINT_EVERY_FILES`/`CHECKPOINT_EVERY`
+/// rather than per file to keep the per-file hot path cheap, and once more
+/// on pause, cancellation and completion.
+async fn checkpoint(pool: &SqlitePool, job_id: &str, cursor: &SyncCursor) {
+    match rmp_serde::to_vec(cursor) {
+        Ok(state) => {
+            let _ = database::update_job_state(pool, job_id, &state, cursor.completed).await;
+        }
+        Err(e) => eprintln!("Failed to encode job cursor for {}: {}", job_id, e),
+    }
+}
+
+async fn run_job(
+    pool: SqlitePool,
+    config: ScannerConfig,
+    handle: JobHandle,
+    mut cursor: SyncCursor,
+    app_handle: tauri::AppHandle,
+) {
+    let total = cursor.work_list.len();
+    let mut last_checkpoint = Instant::now();
+
+    while cursor.completed < cursor.work_list.len() {
+        if handle.is_cancelled() {
+            let _ = database::set_job_status(&pool, &handle.job_id, JobStatus::Cancelled.as_str()).await;
+            checkpoint(&pool, &handle.job_id, &cursor).await;
+            emit_status(&app_handle, &handle.job_id, JobStatus::Cancelled, total, cursor.completed);
+            return;
+        }
+
+        if handle.is_paused() {
+            let _ = database::set_job_status(&pool, &handle.job_id, JobStatus::Paused.as_str()).await;
+            checkpoint(&pool, &handle.job_id, &cursor).await;
+            emit_status(&app_handle, &handle.job_id, JobStatus::Paused, total, cursor.completed);
+
+            while handle.is_paused() && !handle.is_cancelled() {
+                tokio::time::sleep(Duration::from_millis(250)).await;
+            }
+
+            if handle.is_cancelled() {
+                continue;
+            }
+            let _ = database::set_job_status(&pool, &handle.job_id, JobStatus::Running.as_str()).await;
+        }
+
+        let path = PathBuf::from(&cursor.work_list[cursor.completed]);
+        match scanner::process_and_store_file(&pool, &path, &config).await {
+            Ok(has_workflow) => {
+                cursor.stats.total_processed += 1;
+                if has_workflow {
+                    cursor.stats.files_with_workflows += 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("Sync job {} failed on {}: {}", handle.job_id, path.display(), e);
+                cursor.stats.failed_files += 1;
+            }
+        }
+
+        cursor.completed += 1;
+
+        let due_for_checkpoint = cursor.completed % CHECKPOINT_EVERY_FILES == 0
+            || last_checkpoint.elapsed() >= CHECKPOINT_EVERY;
+        if due_for_checkpoint {
+            checkpoint(&pool, &handle.job_id, &cursor).await;
+            last_checkpoint = Instant::now();
+        } else {
+            let _ = database::update_job_progress(&pool, &handle.job_id, cursor.completed).await;
+        }
+
+        let _ = app_handle.emit("sync-progress", SyncProgress {
+            job_id: Some(handle.job_id.clone()),
+            status: "processing".to_string(),
+            current: cursor.completed,
+            total,
+            message: Some(format!("Processing {}/{}", cursor.completed, total)),
+        });
+    }
+
+    checkpoint(&pool, &handle.job_id, &cursor).await;
+    let _ = database::set_job_status(&pool, &handle.job_id, JobStatus::Completed.as_str()).await;
+    emit_status(&app_handle, &handle.job_id, JobStatus::Completed, total, cursor.completed);
+    let _ = app_handle.emit("sync-complete", &cursor.stats);
+}
+
+fn emit_status(app_handle: &tauri::AppHandle, job_id: &str, status: JobStatus, total: usize, completed: usize) {
+    let _ = app_handle.emit("sync-job-status", JobStatusReport {
+        job_id: job_id.to_string(),
+        status: status.as_str().to_string(),
+        total,
+        completed,
+    });
+}
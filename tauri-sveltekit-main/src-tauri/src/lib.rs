@@ -4,6 +4,10 @@ mod database;
 mod parser;
 mod scanner;
 mod thumbnails;
+mod thumbnail_queue;
+mod jobs;
+mod security;
+mod watcher;
 mod commands;
 
 use std::sync::{Arc, Mutex};
@@ -31,6 +35,13 @@ fn get_test_file() -> models::FileEntry {
         dimensions: Some("1024x1024".to_string()),
         duration: None,
         sampler_count: 2,
+        thumbnail_path: None,
+        integrity_status: None,
+        integrity_error: None,
+        content_hash: None,
+        status: None,
+        checked_at: None,
+        file_size: None,
     }
 }
 
@@ -48,14 +59,23 @@ pub fn run() {
             commands::initialize_gallery,
             commands::get_files,
             commands::get_file_by_id,
+            commands::get_broken_files,
+            commands::reconcile_files,
             commands::get_workflow_metadata,
             commands::toggle_favorite,
             commands::batch_favorite,
             commands::delete_file,
             commands::batch_delete,
+            commands::restore_files,
+            commands::purge_trash,
             commands::sync_files,
+            commands::shallow_sync,
+            commands::sync_subpath,
             commands::get_stats,
             commands::get_thumbnail_path,
+            commands::get_thumbnail_variant,
+            commands::request_thumbnails,
+            commands::find_duplicates,
             commands::health_check,
             commands::get_filter_options,
             // New commands
@@ -65,6 +85,20 @@ pub fn run() {
             commands::get_files_filtered,
             commands::create_folder,
             commands::get_config,
+            commands::snapshot_database,
+            commands::list_snapshots,
+            commands::restore_snapshot,
+            commands::create_collection,
+            commands::list_collections,
+            commands::update_collection,
+            commands::delete_collection,
+            commands::get_collection_files,
+            // Sync job commands
+            commands::start_sync_job,
+            commands::pause_job,
+            commands::resume_job,
+            commands::cancel_job,
+            commands::get_active_jobs,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
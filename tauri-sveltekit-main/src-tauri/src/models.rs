@@ -18,6 +18,34 @@ pub struct FileEntry {
     pub dimensions: Option<String>,
     pub duration: Option<String>,
     pub sampler_count: i32,
+    pub thumbnail_path: Option<String>,
+    /// "ok" | "broken" | "unsupported" — only populated when the scanner's
+    /// integrity-check mode is enabled, see `scanner::FileIntegrity`
+    pub integrity_status: Option<String>,
+    pub integrity_error: Option<String>,
+    /// SHA-256 digest of the file's content, computed incrementally (only
+    /// rehashed when `mtime`/size change), so thumbnails/dedup stay keyed to
+    /// the file's content rather than its path and survive moves/renames
+    pub content_hash: Option<String>,
+    /// "present" | "missing" | "modified" | "error" — the on-disk health of
+    /// this row as of `checked_at`. The scanner sets it to "present" when it
+    /// first indexes a file and keeps it on re-index; the `reconcile_files`
+    /// background pass updates it after that
+    pub status: Option<String>,
+    /// When this file was first indexed or last `stat`ed by
+    /// `reconcile_files`, seconds since epoch
+    pub checked_at: Option<f64>,
+    /// File size in bytes as of first indexing or the last reconcile pass,
+    /// used to detect in-place modification alongside mtime
+    pub file_size: Option<i64>,
+}
+
+/// A group of files sharing the same `content_hash` — identical copies or
+/// re-exports of the same generation, for the `find_duplicates` command
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuplicateCluster {
+    pub content_hash: String,
+    pub files: Vec<FileEntry>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -34,6 +62,11 @@ pub struct WorkflowMetadata {
     pub negative_prompt: Option<String>,
     pub width: Option<i64>,
     pub height: Option<i64>,
+    pub seed: Option<i64>,
+    pub denoise: Option<f64>,
+    /// Comma-joined `LoraInfo::name`s applied by this sampler, mirroring how
+    /// `sampler_names` summarizes multiple values into one display string
+    pub lora_names: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -48,12 +81,34 @@ pub struct FolderConfig {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SyncProgress {
+    /// Set when emitted by the `jobs` subsystem so the UI can track multiple
+    /// concurrent sync jobs; `None` for the legacy fire-and-forget `sync_files`
+    pub job_id: Option<String>,
     pub status: String,
     pub current: usize,
     pub total: usize,
     pub message: Option<String>,
 }
 
+/// Per-file outcome of a `batch_delete`/`restore_files` call, so a failure on
+/// one file doesn't hide whether its siblings actually succeeded
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeleteResult {
+    pub file_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Per-file outcome of a `move_files` call, so a failure moving one file
+/// doesn't hide whether its siblings actually moved
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MoveResult {
+    pub file_id: String,
+    pub success: bool,
+    pub new_path: Option<String>,
+    pub error: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FilterOptions {
     pub models: Vec<String>,
@@ -61,6 +116,9 @@ pub struct FilterOptions {
     pub schedulers: Vec<String>,
     pub extensions: Vec<String>,
     pub prefixes: Vec<String>,
+    /// The fixed set of values `FileEntry.status` can take, for the UI's
+    /// "show only missing files" style filter
+    pub statuses: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -82,8 +140,18 @@ pub struct GalleryFilters {
     pub width_max: Option<i64>,
     pub height_min: Option<i64>,
     pub height_max: Option<i64>,
+    pub duration_min: Option<f64>,
+    pub duration_max: Option<f64>,
+    pub has_workflow: Option<bool>,
     pub date_from: Option<String>,
     pub date_to: Option<String>,
+    /// Filter to a single `FileEntry.status` value, e.g. `"missing"` to list
+    /// files the reconciler found gone from disk
+    pub status: Option<String>,
+    /// `"relevance"` orders matches by FTS5 BM25 rank instead of `mtime DESC`
+    /// when `search` is set; any other value (or `None`) keeps the default
+    /// newest-first ordering
+    pub sort_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -99,3 +167,26 @@ pub struct AppConfig {
     pub input_path: Option<String>,
     pub port: Option<u16>,
 }
+
+/// A named, persisted `GalleryFilters` — a "smart folder" that always
+/// reflects whichever files currently match it rather than a point-in-time
+/// list. `file_count` is a live rollup computed with the same predicate
+/// builder as `get_files_filtered`, not a value stored alongside the filters
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub filters: GalleryFilters,
+    pub per_page: usize,
+    pub created_at: i64,
+    pub file_count: usize,
+}
+
+/// A point-in-time database copy written by `database::snapshot_db`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotInfo {
+    pub filename: String,
+    pub path: String,
+    pub created_at: i64,
+    pub size_bytes: u64,
+}
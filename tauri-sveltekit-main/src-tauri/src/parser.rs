@@ -2,7 +2,7 @@
 // Ports the Python ComfyUIWorkflowParser with full dual-format support
 
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 // Node type constants - researched from real-world ComfyUI workflows
@@ -47,7 +47,37 @@ const SCHEDULER_NODE_TYPES: &[&str] = &[
 #[allow(dead_code)]
 const SAMPLER_SELECT_NODE_TYPES: &[&str] = &["KSamplerSelect"];
 
-#[derive(Debug, Clone)]
+// Nodes that just forward a single input to their output, unchanged.
+const PASSTHROUGH_TYPES: &[&str] = &["Reroute", "RerouteNode", "Primitive", "PrimitiveNode"];
+
+const LORA_LOADER_TYPES: &[&str] = &["LoraLoader", "LoraLoaderModelOnly", "LoraTagLoader"];
+
+// Ordered widget names per node type, for resolving `widgets_values` positionally
+// in UI-format workflows (where widgets aren't named, only ordered).
+const WIDGET_INDEX_MAP: &[(&str, &[&str])] = &[
+    ("KSampler", &["seed", "control_after_generate", "steps", "cfg", "sampler_name", "scheduler", "denoise"]),
+    ("KSamplerAdvanced", &["add_noise", "seed", "control_after_generate", "steps", "cfg", "sampler_name", "scheduler", "start_at_step", "end_at_step", "return_with_leftover_noise"]),
+    ("SamplerCustom", &["add_noise", "seed", "control_after_generate", "cfg"]),
+    ("EmptyLatentImage", &["width", "height", "batch_size"]),
+    ("CLIPTextEncode", &["text"]),
+    ("CLIPTextEncodeSDXL", &["width", "height", "crop_w", "crop_h", "target_width", "target_height", "text_g", "text_l"]),
+    ("CheckpointLoaderSimple", &["ckpt_name"]),
+    ("CheckpointLoader", &["config_name", "ckpt_name"]),
+    ("UNETLoader", &["unet_name", "weight_dtype"]),
+    ("UnetLoaderGGUF", &["unet_name"]),
+    ("VAELoader", &["vae_name"]),
+    ("LoraLoader", &["lora_name", "strength_model", "strength_clip"]),
+    ("LoraLoaderModelOnly", &["lora_name", "strength_model"]),
+    ("LoraTagLoader", &["text"]),
+];
+
+fn widget_index_for(node_type: &str, param_name: &str) -> Option<usize> {
+    WIDGET_INDEX_MAP.iter()
+        .find(|(ty, _)| *ty == node_type)
+        .and_then(|(_, names)| names.iter().position(|n| *n == param_name))
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ParsedWorkflow {
     pub model_name: Option<String>,
     pub sampler_name: Option<String>,
@@ -58,6 +88,17 @@ pub struct ParsedWorkflow {
     pub height: Option<i64>,
     pub cfg: Option<f64>,
     pub steps: Option<i64>,
+    pub seed: Option<i64>,
+    pub denoise: Option<f64>,
+    pub vae_name: Option<String>,
+    pub loras: Vec<LoraInfo>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LoraInfo {
+    pub name: String,
+    pub strength_model: f64,
+    pub strength_clip: f64,
 }
 
 #[derive(Debug)]
@@ -202,10 +243,40 @@ impl ComfyUIWorkflowParser {
         }
     }
     
+    /// Follow a pass-through node's single input link regardless of its name,
+    /// used for `Reroute`/`Primitive` nodes that just forward whatever they're fed.
+    fn get_single_input_source_node(&self, node: &Value) -> Option<Value> {
+        match self.format {
+            WorkflowFormat::UI => {
+                let inputs = node.as_object()?.get("inputs")?.as_array()?;
+                for input_def in inputs {
+                    let input_obj = input_def.as_object()?;
+                    if let Some(link_id) = input_obj.get("link").and_then(|l| l.as_i64()) {
+                        if let Some((source_id, _)) = self.links_map.get(&link_id) {
+                            return self.nodes_by_id.get(source_id).cloned();
+                        }
+                    }
+                }
+                None
+            }
+            WorkflowFormat::API => {
+                let inputs = node.as_object()?.get("inputs")?.as_object()?;
+                for value in inputs.values() {
+                    if let Some(input_ref) = value.as_array() {
+                        if let Some(source_id) = input_ref.first().and_then(|v| v.as_str()) {
+                            return self.nodes_by_id.get(source_id).cloned();
+                        }
+                    }
+                }
+                None
+            }
+        }
+    }
+
     fn get_widget_value(&self, node: &Value, param_name: &str) -> Option<Value> {
         match self.format {
             WorkflowFormat::UI => {
-                // UI format: widgets_values array or properties
+                // UI format: properties, then widgets_values resolved via widget_index_for
                 if let Some(node_obj) = node.as_object() {
                     // Try properties first
                     if let Some(props) = node_obj.get("properties").and_then(|p| p.as_object()) {
@@ -213,12 +284,17 @@ impl ComfyUIWorkflowParser {
                             return Some(value.clone());
                         }
                     }
-                    
-                    // Try widgets_values with widget_idx_map
-                    // For simplicity, we'll search by name in title or node type
-                    if let Some(widgets) = node_obj.get("widgets_values") {
-                        // This is simplified - full implementation would use widget_idx_map
-                        return Some(widgets.clone());
+
+                    if let Some(widgets) = node_obj.get("widgets_values").and_then(|w| w.as_array()) {
+                        // Resolve positionally via the known widget order for this node type
+                        if let Some(node_type) = self.get_node_type(node) {
+                            if let Some(idx) = widget_index_for(&node_type, param_name) {
+                                return widgets.get(idx).cloned();
+                            }
+                        }
+
+                        // Unknown node type or parameter: fall back to returning the whole array
+                        return Some(Value::Array(widgets.clone()));
                     }
                 }
                 None
@@ -242,25 +318,38 @@ impl ComfyUIWorkflowParser {
         max_hops: usize,
     ) -> Option<Value> {
         let mut current_node_id = start_node_id.to_string();
-        
-        for _ in 0..max_hops {
+        let mut visited: HashSet<String> = HashSet::new();
+
+        for hop in 0..max_hops {
+            if !visited.insert(current_node_id.clone()) {
+                // Cycle detected (e.g. malformed Reroute loop) - bail out
+                return None;
+            }
+
             let node = self.nodes_by_id.get(&current_node_id)?;
             let node_type = self.get_node_type(node)?;
-            
-            // Stop if we found target type
-            if stop_at_types.contains(&node_type.as_str()) {
+
+            // Stop if we found target type - but never on the start node
+            // itself (hop 0). `start_node_id` is the node we're tracing an
+            // input *from*, and its own type can coincide with
+            // `stop_at_types` (e.g. `parse_pipelines` walks a sampler's
+            // `latent_image` input looking for another `SAMPLER_TYPES` node,
+            // starting from a sampler); stopping immediately would return
+            // the start node instead of ever following its input edge.
+            if hop > 0 && stop_at_types.contains(&node_type.as_str()) {
                 return Some(node.clone());
             }
-            
-            // Handle Primitive nodes (pass-through)
-            if node_type == "Primitive" || node_type == "PrimitiveNode" {
-                // Try to find what this primitive connects to
-                // This is simplified - full implementation would trace all connections
-                continue;
-            }
-            
+
+            // Pass-through nodes (Reroute/Primitive) forward whatever they're fed,
+            // so follow their single input link regardless of `input_name`.
+            let source_node = if PASSTHROUGH_TYPES.contains(&node_type.as_str()) {
+                self.get_single_input_source_node(node)
+            } else {
+                self.get_input_source_node(node, input_name)
+            };
+
             // Try to follow the input connection
-            if let Some(source_node) = self.get_input_source_node(node, input_name) {
+            if let Some(source_node) = source_node {
                 if let Some(source_id_val) = source_node.as_object().and_then(|n| n.get("id")) {
                     current_node_id = if let Some(s) = source_id_val.as_str() {
                         s.to_string()
@@ -272,21 +361,145 @@ impl ComfyUIWorkflowParser {
                     continue;
                 }
             }
-            
+
             break;
         }
-        
+
         None
     }
-    
+
+    /// Like `find_source_node`, but collects every node of `collect_types` encountered
+    /// along the path instead of stopping at the first match - used to walk a LoRA
+    /// stack where several `LoraLoader` nodes can be chained before the checkpoint.
+    fn find_source_nodes_along(
+        &self,
+        start_node_id: &str,
+        input_name: &str,
+        collect_types: &[&str],
+        max_hops: usize,
+    ) -> Vec<Value> {
+        let mut current_node_id = start_node_id.to_string();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut collected = Vec::new();
+
+        for _ in 0..max_hops {
+            if !visited.insert(current_node_id.clone()) {
+                break;
+            }
+
+            let node = match self.nodes_by_id.get(&current_node_id) {
+                Some(n) => n,
+                None => break,
+            };
+            let node_type = match self.get_node_type(node) {
+                Some(t) => t,
+                None => break,
+            };
+
+            if collect_types.contains(&node_type.as_str()) {
+                collected.push(node.clone());
+            }
+
+            let source_node = if PASSTHROUGH_TYPES.contains(&node_type.as_str()) {
+                self.get_single_input_source_node(node)
+            } else {
+                self.get_input_source_node(node, input_name)
+            };
+
+            if let Some(source_node) = source_node {
+                if let Some(source_id_val) = source_node.as_object().and_then(|n| n.get("id")) {
+                    current_node_id = if let Some(s) = source_id_val.as_str() {
+                        s.to_string()
+                    } else if let Some(i) = source_id_val.as_i64() {
+                        i.to_string()
+                    } else {
+                        break;
+                    };
+                    continue;
+                }
+            }
+
+            break;
+        }
+
+        collected
+    }
+
+    /// Flattens `parse_pipelines` into the `sampler_index`-ordered rows
+    /// `extract_workflow_metadata` persists, so a refiner/upscale chain is
+    /// stored base-first rather than in whatever order the node IDs
+    /// happened to sort to.
     pub fn parse(&self) -> Vec<ParsedWorkflow> {
+        self.parse_pipelines().into_iter().flatten().collect()
+    }
+
+    /// Groups samplers that feed into each other (base -> refiner ->
+    /// upscale chains) into a single ordered pipeline instead of returning disconnected rows.
+    pub fn parse_pipelines(&self) -> Vec<Vec<ParsedWorkflow>> {
         let sampler_nodes = self.find_sampler_nodes();
-        
-        sampler_nodes.iter()
-            .filter_map(|node| self.process_sampler(node))
+        let sampler_ids: Vec<String> = sampler_nodes.iter()
+            .filter_map(|n| self.node_id_string(n))
+            .collect();
+        let sampler_id_set: HashSet<&str> = sampler_ids.iter().map(|s| s.as_str()).collect();
+
+        // Edge: sampler -> the predecessor sampler whose LATENT output feeds it
+        let mut predecessor: HashMap<String, String> = HashMap::new();
+        for id in &sampler_ids {
+            for input_name in ["latent_image", "samples"] {
+                if let Some(src) = self.find_source_node(id, input_name, SAMPLER_TYPES, 20) {
+                    if let Some(src_id) = self.node_id_string(&src) {
+                        if sampler_id_set.contains(src_id.as_str()) && &src_id != id {
+                            predecessor.insert(id.clone(), src_id);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Invert into predecessor -> successors, so each component can be walked forward
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for (succ, pred) in &predecessor {
+            children.entry(pred.clone()).or_default().push(succ.clone());
+        }
+        for succs in children.values_mut() {
+            succs.sort_by_key(|id| id.parse::<i64>().unwrap_or(0));
+        }
+
+        // Roots are samplers with no predecessor - the base generation of each pipeline
+        let roots = sampler_ids.iter().filter(|id| !predecessor.contains_key(*id));
+
+        roots
+            .filter_map(|root| {
+                let mut ordered_ids = Vec::new();
+                self.collect_pipeline_chain(root, &children, &mut ordered_ids);
+
+                let pipeline: Vec<ParsedWorkflow> = ordered_ids.iter()
+                    .filter_map(|id| self.nodes_by_id.get(id))
+                    .filter_map(|node| self.process_sampler(node))
+                    .collect();
+
+                if pipeline.is_empty() { None } else { Some(pipeline) }
+            })
             .collect()
     }
-    
+
+    /// Depth-first walk of a sampler pipeline component, parent before children.
+    fn collect_pipeline_chain(&self, node_id: &str, children: &HashMap<String, Vec<String>>, out: &mut Vec<String>) {
+        out.push(node_id.to_string());
+        if let Some(kids) = children.get(node_id) {
+            for kid in kids {
+                self.collect_pipeline_chain(kid, children, out);
+            }
+        }
+    }
+
+    fn node_id_string(&self, node: &Value) -> Option<String> {
+        let id = node.as_object()?.get("id")?;
+        id.as_str().map(|s| s.to_string())
+            .or_else(|| id.as_i64().map(|i| i.to_string()))
+    }
+
     fn find_sampler_nodes(&self) -> Vec<Value> {
         let mut samplers: Vec<(String, Value)> = self.nodes_by_id.iter()
             .filter_map(|(id, node)| {
@@ -312,7 +525,11 @@ impl ComfyUIWorkflowParser {
         let (pos_prompts, neg_prompts) = self.extract_prompts(sampler_node);
         let (width, height) = self.extract_dimensions(sampler_node);
         let (cfg, steps) = self.extract_parameters(sampler_node);
-        
+        let seed = self.extract_seed(sampler_node);
+        let denoise = self.extract_denoise(sampler_node);
+        let vae_name = self.extract_vae(sampler_node);
+        let loras = self.extract_loras(sampler_node);
+
         Some(ParsedWorkflow {
             model_name,
             sampler_name,
@@ -323,6 +540,10 @@ impl ComfyUIWorkflowParser {
             height,
             cfg,
             steps,
+            seed,
+            denoise,
+            vae_name,
+            loras,
         })
     }
     
@@ -423,13 +644,177 @@ impl ComfyUIWorkflowParser {
         
         (None, None)
     }
+
+    fn extract_seed(&self, sampler_node: &Value) -> Option<i64> {
+        self.get_widget_value(sampler_node, "seed")
+            .and_then(|v| v.as_i64())
+    }
+
+    fn extract_denoise(&self, sampler_node: &Value) -> Option<f64> {
+        self.get_widget_value(sampler_node, "denoise")
+            .and_then(|v| v.as_f64())
+    }
+
+    fn extract_vae(&self, sampler_node: &Value) -> Option<String> {
+        let node_id_string = sampler_node.as_object()
+            .and_then(|n| n.get("id"))
+            .and_then(|id| {
+                id.as_str().map(|s| s.to_string())
+                    .or_else(|| id.as_i64().map(|i| i.to_string()))
+            })
+            .unwrap_or_else(|| String::new());
+
+        let vae_node = self.find_source_node(&node_id_string, "vae", &["VAELoader"], 20)?;
+
+        self.get_widget_value(&vae_node, "vae_name")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+    }
+
+    fn extract_loras(&self, sampler_node: &Value) -> Vec<LoraInfo> {
+        let node_id_string = sampler_node.as_object()
+            .and_then(|n| n.get("id"))
+            .and_then(|id| {
+                id.as_str().map(|s| s.to_string())
+                    .or_else(|| id.as_i64().map(|i| i.to_string()))
+            })
+            .unwrap_or_else(|| String::new());
+
+        self.find_source_nodes_along(&node_id_string, "model", LORA_LOADER_TYPES, 20)
+            .iter()
+            .filter_map(|lora_node| {
+                let name = self.get_widget_value(lora_node, "lora_name")
+                    .and_then(|v| v.as_str().map(|s| s.to_string()))?;
+
+                let strength_model = self.get_widget_value(lora_node, "strength_model")
+                    .and_then(|v| v.as_f64())
+                    .unwrap_or(1.0);
+
+                let strength_clip = self.get_widget_value(lora_node, "strength_clip")
+                    .and_then(|v| v.as_f64())
+                    .unwrap_or(strength_model);
+
+                Some(LoraInfo {
+                    name,
+                    strength_model,
+                    strength_clip,
+                })
+            })
+            .collect()
+    }
 }
 
 /// Extract workflow metadata from JSON string
 pub fn extract_workflow_metadata(workflow_str: &str, file_path: &Path) -> Result<Vec<ParsedWorkflow>, String> {
     let workflow_data: Value = serde_json::from_str(workflow_str)
         .map_err(|e| format!("Failed to parse workflow JSON: {}", e))?;
-    
+
     let parser = ComfyUIWorkflowParser::new(workflow_data, file_path)?;
     Ok(parser.parse())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser_for(workflow_json: &str) -> ComfyUIWorkflowParser {
+        let workflow_data: Value = serde_json::from_str(workflow_json).unwrap();
+        ComfyUIWorkflowParser::new(workflow_data, Path::new("test.png")).unwrap()
+    }
+
+    #[test]
+    fn find_source_node_traces_through_a_single_reroute() {
+        let parser = parser_for(r#"{
+            "sampler": {"class_type": "KSampler", "inputs": {"model": ["reroute", 0]}},
+            "reroute": {"class_type": "Reroute", "inputs": {"input": ["loader", 0]}},
+            "loader": {"class_type": "CheckpointLoaderSimple", "inputs": {}}
+        }"#);
+
+        let found = parser.find_source_node("sampler", "model", MODEL_LOADER_TYPES, 20);
+        let node_type = found.as_ref().and_then(|n| parser.get_node_type(n));
+        assert_eq!(node_type.as_deref(), Some("CheckpointLoaderSimple"));
+    }
+
+    #[test]
+    fn find_source_node_traces_through_a_primitive_node() {
+        let parser = parser_for(r#"{
+            "sampler": {"class_type": "KSampler", "inputs": {"model": ["prim", 0]}},
+            "prim": {"class_type": "PrimitiveNode", "inputs": {"value": ["loader", 0]}},
+            "loader": {"class_type": "UNETLoader", "inputs": {}}
+        }"#);
+
+        let found = parser.find_source_node("sampler", "model", MODEL_LOADER_TYPES, 20);
+        let node_type = found.as_ref().and_then(|n| parser.get_node_type(n));
+        assert_eq!(node_type.as_deref(), Some("UNETLoader"));
+    }
+
+    #[test]
+    fn find_source_node_traces_through_a_chain_of_reroutes() {
+        let parser = parser_for(r#"{
+            "sampler": {"class_type": "KSampler", "inputs": {"model": ["r1", 0]}},
+            "r1": {"class_type": "Reroute", "inputs": {"input": ["r2", 0]}},
+            "r2": {"class_type": "Reroute", "inputs": {"input": ["loader", 0]}},
+            "loader": {"class_type": "CheckpointLoaderSimple", "inputs": {}}
+        }"#);
+
+        let found = parser.find_source_node("sampler", "model", MODEL_LOADER_TYPES, 20);
+        assert!(found.is_some());
+    }
+
+    #[test]
+    fn find_source_node_bails_out_of_a_reroute_cycle() {
+        let parser = parser_for(r#"{
+            "sampler": {"class_type": "KSampler", "inputs": {"model": ["r1", 0]}},
+            "r1": {"class_type": "Reroute", "inputs": {"input": ["r2", 0]}},
+            "r2": {"class_type": "Reroute", "inputs": {"input": ["r1", 0]}}
+        }"#);
+
+        // r1 <-> r2 never reach a MODEL_LOADER_TYPES node; the cycle guard
+        // must return None instead of looping until `max_hops` (or forever).
+        let found = parser.find_source_node("sampler", "model", MODEL_LOADER_TYPES, 20);
+        assert!(found.is_none());
+    }
+
+    #[test]
+    fn find_source_nodes_along_collects_every_lora_in_a_reroute_chain() {
+        let parser = parser_for(r#"{
+            "sampler": {"class_type": "KSampler", "inputs": {"model": ["lora2", 0]}},
+            "lora2": {"class_type": "LoraLoader", "inputs": {"model": ["reroute", 0]}, "widgets_values": ["lora_b.safetensors", 1.0, 1.0]},
+            "reroute": {"class_type": "Reroute", "inputs": {"input": ["lora1", 0]}},
+            "lora1": {"class_type": "LoraLoader", "inputs": {"model": ["loader", 0]}, "widgets_values": ["lora_a.safetensors", 1.0, 1.0]},
+            "loader": {"class_type": "CheckpointLoaderSimple", "inputs": {}}
+        }"#);
+
+        let found = parser.find_source_nodes_along("sampler", "model", LORA_LOADER_TYPES, 20);
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn find_source_node_traces_past_a_same_typed_start_node() {
+        // The start node's own type is in `stop_at_types` here (both are
+        // samplers) - `find_source_node` must still walk to the predecessor
+        // instead of immediately returning the start node on hop 0.
+        let parser = parser_for(r#"{
+            "base": {"class_type": "KSampler", "inputs": {}},
+            "refiner": {"class_type": "KSampler", "inputs": {"latent_image": ["base", 0]}}
+        }"#);
+
+        let found = parser.find_source_node("refiner", "latent_image", SAMPLER_TYPES, 20);
+        let found_id = found.as_ref()
+            .and_then(|n| n.as_object())
+            .and_then(|o| o.get("id"))
+            .and_then(|id| id.as_str());
+        assert_eq!(found_id, Some("base"));
+    }
+
+    #[test]
+    fn parse_pipelines_groups_a_base_and_refiner_into_one_chain() {
+        let parser = parser_for(r#"{
+            "base": {"class_type": "KSampler", "inputs": {}},
+            "refiner": {"class_type": "KSampler", "inputs": {"latent_image": ["base", 0]}}
+        }"#);
+
+        let pipelines = parser.parse_pipelines();
+        assert_eq!(pipelines.len(), 1, "base and refiner should be one pipeline, not two disconnected roots");
+        assert_eq!(pipelines[0].len(), 2);
+    }
+}
@@ -4,14 +4,15 @@
 use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
 use walkdir::WalkDir;
-use rayon::prelude::*;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use serde::Serialize;
+use serde_json::Value;
 
 use crate::models::{FileEntry, SyncProgress};
 use crate::database;
 use crate::parser;
+use crate::thumbnails::{self, ThumbnailConfig};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -24,12 +25,17 @@ pub struct ScannerConfig {
     pub video_extensions: Vec<String>,
     pub audio_extensions: Vec<String>,
     pub animated_extensions: Vec<String>,
+    /// When enabled, `process_file` fully decodes each image and runs each
+    /// video/audio file through `ffmpeg` from its start and over its last
+    /// second to detect truncated
+    /// downloads or broken renders (see `FileIntegrity`)
+    pub integrity_check: bool,
 }
 
 impl ScannerConfig {
     pub fn new(output_path: PathBuf) -> Self {
         let thumbnail_cache_dir = output_path.join("thumbnails_cache");
-        
+
         Self {
             output_path: output_path.clone(),
             input_path: None,
@@ -50,11 +56,76 @@ impl ScannerConfig {
             animated_extensions: vec![
                 ".gif".to_string(), ".webp".to_string(),
             ],
+            integrity_check: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Result of an integrity decode pass over a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileIntegrity {
+    Ok,
+    Broken,
+    Unsupported,
+}
+
+impl FileIntegrity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileIntegrity::Ok => "ok",
+            FileIntegrity::Broken => "broken",
+            FileIntegrity::Unsupported => "unsupported",
+        }
+    }
+}
+
+/// Fully decode an image, or decode the start and the last second of a
+/// video/audio file via `ffmpeg`, to catch truncated downloads and broken ComfyUI renders
+/// that `extract_media_metadata` would otherwise silently ignore
+fn check_integrity(filepath: &Path, file_type: &str) -> (FileIntegrity, Option<String>) {
+    match file_type {
+        "image" => match image::open(filepath) {
+            Ok(_) => (FileIntegrity::Ok, None),
+            Err(e) => (FileIntegrity::Broken, Some(e.to_string())),
+        },
+        "video" | "audio" => {
+            // Decode the first video frame (the whole stream for audio-only
+            // files), then the last second; a truncated or corrupt file
+            // typically fails to decode in one of these
+            let first = std::process::Command::new("ffmpeg")
+                .arg("-v").arg("error")
+                .arg("-i").arg(filepath)
+                .arg("-frames:v").arg("1")
+                .arg("-f").arg("null")
+                .arg("-")
+                .output();
+
+            let last = std::process::Command::new("ffmpeg")
+                .arg("-v").arg("error")
+                .arg("-sseof").arg("-1")
+                .arg("-i").arg(filepath)
+                .arg("-f").arg("null")
+                .arg("-")
+                .output();
+
+            match (first, last) {
+                (Ok(f), Ok(l)) if f.status.success() && l.status.success() => (FileIntegrity::Ok, None),
+                (Ok(f), Ok(l)) => {
+                    let stderr = if !f.status.success() {
+                        String::from_utf8_lossy(&f.stderr).to_string()
+                    } else {
+                        String::from_utf8_lossy(&l.stderr).to_string()
+                    };
+                    (FileIntegrity::Broken, Some(stderr))
+                }
+                (Err(e), _) | (_, Err(e)) => (FileIntegrity::Broken, Some(format!("Failed to run ffmpeg: {}", e))),
+            }
+        }
+        _ => (FileIntegrity::Unsupported, None),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
 pub struct ScanStats {
     pub total_processed: usize,
     pub files_with_workflows: usize,
@@ -76,54 +147,507 @@ impl ScanStats {
 }
 
 /// Scan all files in output directory recursively
-pub fn scan_directory(config: &ScannerConfig) -> Result<Vec<PathBuf>, String> {
+/// True if `path` has a media extension this scanner knows how to process
+fn is_supported_media_file(path: &Path, config: &ScannerConfig) -> bool {
+    let Some(ext) = path.extension() else {
+        return false;
+    };
+    let ext_str = format!(".{}", ext.to_string_lossy().to_lowercase());
+
+    // Skip JSON and database files
+    if ext_str == ".json" || ext_str == ".sqlite" || ext_str == ".db" {
+        return false;
+    }
+
+    config.image_extensions.contains(&ext_str)
+        || config.video_extensions.contains(&ext_str)
+        || config.audio_extensions.contains(&ext_str)
+}
+
+/// Skip thumbnail/database cache directories and dotfiles while walking
+fn is_walkable_entry(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    !file_name.starts_with("thumbnails_cache")
+        && !file_name.starts_with("smartgallery_cache")
+        && !file_name.starts_with(".")
+}
+
+/// Recursively list supported media files under `root`, for both the
+/// full-library walk (`scan_directory`) and a single subtree re-index
+/// (`sync_subpath`)
+fn scan_dir_recursive(root: &Path, config: &ScannerConfig) -> Result<Vec<PathBuf>, String> {
     let mut files = Vec::new();
-    
-    let walker = WalkDir::new(&config.output_path)
+
+    let walker = WalkDir::new(root)
         .follow_links(false)
         .into_iter()
-        .filter_entry(|e| {
-            // Skip thumbnail and database cache directories
-            let path = e.path();
-            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-            !file_name.starts_with("thumbnails_cache") 
-                && !file_name.starts_with("smartgallery_cache")
-                && !file_name.starts_with(".")
-        });
-    
+        .filter_entry(|e| is_walkable_entry(e.path()));
+
     for entry in walker {
         match entry {
             Ok(entry) => {
                 let path = entry.path();
-                if path.is_file() {
-                    // Check if it's a media file
-                    if let Some(ext) = path.extension() {
-                        let ext_str = format!(".{}", ext.to_string_lossy().to_lowercase());
-                        
-                        // Skip JSON and database files
-                        if ext_str == ".json" || ext_str == ".sqlite" || ext_str == ".db" {
-                            continue;
+                if path.is_file() && is_supported_media_file(path, config) {
+                    files.push(path.to_path_buf());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error walking directory: {}", e);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+pub fn scan_directory(config: &ScannerConfig) -> Result<Vec<PathBuf>, String> {
+    scan_dir_recursive(&config.output_path, config)
+}
+
+/// How many directory-walker threads `full_sync` spawns. Bounded rather than
+/// one-per-subtree so a library with thousands of small output folders
+/// doesn't spawn thousands of OS threads.
+const WALK_WORKER_COUNT: usize = 4;
+
+/// Size of the bounded `mpsc` channel between walker threads and the single
+/// DB-writer thread. Small enough to apply backpressure on fast walkers
+/// outrunning the writer, large enough to keep the writer from starving.
+const WALK_CHANNEL_CAPACITY: usize = 512;
+
+/// Flush accumulated entries to the DB every this many, so a single
+/// transaction amortizes WAL fsync cost without holding an open transaction
+/// across the whole sync.
+const WRITE_BATCH_SIZE: usize = 500;
+
+/// One walk unit handed to a worker thread: either the output directory's own
+/// immediate files (non-recursive, so it isn't re-walked by every
+/// subdirectory's unit) or one immediate subdirectory, walked recursively.
+enum WalkUnit {
+    RootFiles,
+    Subtree(PathBuf),
+}
+
+/// Split `output_path` into independent units a bounded worker pool can walk
+/// concurrently: one per immediate subdirectory (each walked recursively) plus
+/// one for the loose files directly under the root. Subdirectories don't
+/// overlap, so no locking is needed to keep workers from double-visiting a
+/// path.
+fn list_walk_units(output_path: &Path) -> Vec<WalkUnit> {
+    let mut units = vec![WalkUnit::RootFiles];
+
+    let Ok(entries) = std::fs::read_dir(output_path) else {
+        return units;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && is_walkable_entry(&path) {
+            units.push(WalkUnit::Subtree(path));
+        }
+    }
+
+    units
+}
+
+/// A file discovered by a walker thread, already fully processed and ready
+/// for the writer thread to persist in its next batch.
+struct WalkedFile {
+    file_entry: FileEntry,
+    workflow_metadata: Vec<crate::models::WorkflowMetadata>,
+}
+
+fn workflow_metadata_for(file_id: &str, parsed: &[parser::ParsedWorkflow]) -> Vec<crate::models::WorkflowMetadata> {
+    parsed.iter().enumerate().map(|(i, p)| crate::models::WorkflowMetadata {
+        id: None,
+        file_id: file_id.to_string(),
+        sampler_index: i as i32,
+        model_name: p.model_name.clone(),
+        sampler_name: p.sampler_name.clone(),
+        scheduler: p.scheduler.clone(),
+        cfg: p.cfg,
+        steps: p.steps,
+        positive_prompt: Some(p.positive_prompt.clone()),
+        negative_prompt: Some(p.negative_prompt.clone()),
+        width: p.width,
+        height: p.height,
+        seed: p.seed,
+        denoise: p.denoise,
+        lora_names: lora_names(&p.loras),
+    }).collect()
+}
+
+/// Comma-join `LoraInfo::name`s for storage in `workflow_metadata.lora_names`,
+/// mirroring how `sampler_names` summarizes multiple values into one string
+fn lora_names(loras: &[parser::LoraInfo]) -> Option<String> {
+    if loras.is_empty() {
+        return None;
+    }
+    Some(loras.iter().map(|l| l.name.as_str()).collect::<Vec<_>>().join(", "))
+}
+
+/// Parallel directory walker backing `full_sync`: a bounded pool of worker
+/// threads each walk one subtree of `output_path` (see `list_walk_units`),
+/// processing files as they're found and sending the results over a bounded
+/// channel to a single writer thread. The writer batches `upsert_file`-
+/// equivalent writes into transactions of `WRITE_BATCH_SIZE` rows, so the
+/// commit cost of a large first-time index is paid once per batch rather than
+/// once per file, while the walk and metadata/workflow extraction (the
+/// expensive part) run fully in parallel across threads.
+fn parallel_scan_and_store(
+    pool: sqlx::SqlitePool,
+    config: ScannerConfig,
+    db_mtimes: HashMap<String, f64>,
+    progress_callback: Option<Box<dyn Fn(SyncProgress) + Send + Sync>>,
+) -> ScanStats {
+    let units = Arc::new(Mutex::new(list_walk_units(&config.output_path).into_iter().collect::<std::collections::VecDeque<_>>()));
+    let db_mtimes = Arc::new(db_mtimes);
+    let discovered = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let failed = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let (tx, rx) = std::sync::mpsc::sync_channel::<WalkedFile>(WALK_CHANNEL_CAPACITY);
+
+    let writer_stats = {
+        let progress_callback = progress_callback;
+        let discovered = discovered.clone();
+        std::thread::spawn(move || {
+            let mut stats = ScanStats::new();
+            let mut batch: Vec<(FileEntry, Vec<crate::models::WorkflowMetadata>)> = Vec::with_capacity(WRITE_BATCH_SIZE);
+
+            let mut flush = |batch: &mut Vec<(FileEntry, Vec<crate::models::WorkflowMetadata>)>| {
+                if batch.is_empty() {
+                    return;
+                }
+                if let Err(e) = tauri::async_runtime::block_on(database::upsert_files_tx(&pool, batch)) {
+                    eprintln!("Failed to commit batch of {} files: {}", batch.len(), e);
+                }
+                batch.clear();
+            };
+
+            for walked in rx {
+                stats.total_processed += 1;
+                if walked.file_entry.has_workflow {
+                    stats.files_with_workflows += 1;
+                    stats.metadata_extracted += walked.workflow_metadata.len();
+                }
+                batch.push((walked.file_entry, walked.workflow_metadata));
+
+                if batch.len() >= WRITE_BATCH_SIZE {
+                    flush(&mut batch);
+                }
+
+                if let Some(ref callback) = progress_callback {
+                    callback(SyncProgress {
+                        job_id: None,
+                        status: "processing".to_string(),
+                        current: stats.total_processed,
+                        total: discovered.load(std::sync::atomic::Ordering::Relaxed),
+                        message: Some(format!("Processing {}/{}", stats.total_processed, discovered.load(std::sync::atomic::Ordering::Relaxed))),
+                    });
+                }
+            }
+
+            flush(&mut batch);
+            stats
+        })
+    };
+
+    let pool_for_workers = pool.clone();
+    let workers: Vec<_> = (0..WALK_WORKER_COUNT).map(|_| {
+        let units = units.clone();
+        let db_mtimes = db_mtimes.clone();
+        let discovered = discovered.clone();
+        let failed = failed.clone();
+        let tx = tx.clone();
+        let pool = pool_for_workers.clone();
+        let config = config.clone();
+
+        std::thread::spawn(move || {
+            loop {
+                let unit = match units.lock().unwrap().pop_front() {
+                    Some(unit) => unit,
+                    None => break,
+                };
+
+                let paths = match &unit {
+                    WalkUnit::RootFiles => scan_folder_shallow(&config.output_path, &config),
+                    WalkUnit::Subtree(dir) => scan_dir_recursive(dir, &config),
+                };
+                let Ok(paths) = paths else { continue };
+
+                for path in paths {
+                    discovered.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+                    let path_str = path.to_string_lossy().to_string();
+                    let disk_mtime = get_mtime(&path).unwrap_or(0.0);
+                    let is_new_or_changed = match db_mtimes.get(&path_str) {
+                        Some(db_mtime) => disk_mtime as i64 > *db_mtime as i64,
+                        None => true,
+                    };
+                    if !is_new_or_changed {
+                        continue;
+                    }
+
+                    let result = tauri::async_runtime::block_on(async {
+                        let (mut file_entry, parsed) = process_file_cached(&pool, &path, &config).await?;
+                        if let Ok(Some(existing)) = database::get_file_by_id(&pool, &file_entry.id).await {
+                            file_entry.is_favorite = existing.is_favorite;
+                            file_entry.status = existing.status;
+                            file_entry.checked_at = existing.checked_at;
+                            file_entry.file_size = existing.file_size;
+                            if existing.content_hash != file_entry.content_hash {
+                                database::delete_thumbnail_variants(&pool, &file_entry.id).await?;
+                            }
                         }
-                        
-                        // Check if it's a supported media file
-                        if config.image_extensions.contains(&ext_str)
-                            || config.video_extensions.contains(&ext_str)
-                            || config.audio_extensions.contains(&ext_str)
-                        {
-                            files.push(path.to_path_buf());
+                        let workflow_metadata = workflow_metadata_for(&file_entry.id, &parsed);
+                        Ok::<_, String>(WalkedFile { file_entry, workflow_metadata })
+                    });
+
+                    match result {
+                        Ok(walked) => {
+                            if tx.send(walked).is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to process file {}: {}", path.display(), e);
+                            failed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                         }
                     }
                 }
             }
+        })
+    }).collect();
+
+    drop(tx);
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    let mut stats = writer_stats.join().unwrap_or_else(|_| ScanStats::new());
+    stats.failed_files += failed.load(std::sync::atomic::Ordering::Relaxed);
+    stats
+}
+
+/// List the immediate (non-recursive) media files of a single directory, for
+/// `shallow_sync`
+fn scan_folder_shallow(folder: &Path, config: &ScannerConfig) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+
+    let walker = WalkDir::new(folder)
+        .max_depth(1)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| is_walkable_entry(e.path()));
+
+    for entry in walker {
+        match entry {
+            Ok(entry) => {
+                let path = entry.path();
+                if path.is_file() && is_supported_media_file(path, config) {
+                    files.push(path.to_path_buf());
+                }
+            }
             Err(e) => {
-                eprintln!("Error walking directory: {}", e);
+                eprintln!("Error walking folder {}: {}", folder.display(), e);
             }
         }
     }
-    
+
     Ok(files)
 }
 
+/// Re-index a single folder's immediate contents against the DB rows whose
+/// path lives directly under it: add new files, update changed ones, and
+/// remove rows for files no longer on disk. Used by `shallow_sync` and the
+/// filesystem watcher so a single new/changed ComfyUI output doesn't require
+/// a full `full_sync` pass over the whole tree.
+pub async fn shallow_sync(
+    pool: &sqlx::SqlitePool,
+    config: &ScannerConfig,
+    folder: &Path,
+) -> Result<ScanStats, String> {
+    let mut stats = ScanStats::new();
+
+    let folder_str = folder.to_string_lossy().to_string();
+    let db_files: HashMap<String, f64> = database::get_file_paths_in_folder(pool, &folder_str)
+        .await?
+        .into_iter()
+        .collect();
+
+    let disk_paths = scan_folder_shallow(folder, config)?;
+    let mut seen_paths: HashSet<String> = HashSet::new();
+
+    for path in &disk_paths {
+        let path_str = path.to_string_lossy().to_string();
+        seen_paths.insert(path_str.clone());
+
+        let disk_mtime = get_mtime(path).unwrap_or(0.0);
+        let is_new_or_changed = match db_files.get(&path_str) {
+            Some(db_mtime) => disk_mtime as i64 > *db_mtime as i64,
+            None => true,
+        };
+
+        if !is_new_or_changed {
+            continue;
+        }
+
+        match process_and_store_file(pool, path, config).await {
+            Ok(has_workflow) => {
+                stats.total_processed += 1;
+                if has_workflow {
+                    stats.files_with_workflows += 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("Shallow sync failed on {}: {}", path.display(), e);
+                stats.failed_files += 1;
+            }
+        }
+    }
+
+    // Anything still in the DB for this folder but no longer on disk was
+    // deleted/moved out from under us
+    let stale_paths: Vec<String> = db_files.keys()
+        .filter(|path| !seen_paths.contains(*path))
+        .cloned()
+        .collect();
+    if !stale_paths.is_empty() {
+        database::delete_files_by_path(pool, &stale_paths).await?;
+    }
+
+    Ok(stats)
+}
+
+/// Re-index a single output subfolder recursively — e.g. after a burst of
+/// new files several directories deep — without walking the rest of the
+/// library. Diffs the subtree's disk listing against the DB's rows for that
+/// path prefix (`get_file_paths_under_prefix`), upserting new/changed files and
+/// deleting rows for files no longer present under the prefix. The recursive
+/// counterpart to `shallow_sync`'s single-directory (non-recursive) re-index.
+pub async fn sync_subpath(
+    pool: &sqlx::SqlitePool,
+    config: &ScannerConfig,
+    folder: &Path,
+) -> Result<ScanStats, String> {
+    let mut stats = ScanStats::new();
+
+    let folder_str = folder.to_string_lossy().to_string();
+    let db_files: HashMap<String, f64> = database::get_file_paths_under_prefix(pool, &folder_str)
+        .await?
+        .into_iter()
+        .collect();
+
+    let disk_paths = scan_dir_recursive(folder, config)?;
+    let mut seen_paths: HashSet<String> = HashSet::new();
+
+    for path in &disk_paths {
+        let path_str = path.to_string_lossy().to_string();
+        seen_paths.insert(path_str.clone());
+
+        let disk_mtime = get_mtime(path).unwrap_or(0.0);
+        let is_new_or_changed = match db_files.get(&path_str) {
+            Some(db_mtime) => disk_mtime as i64 > *db_mtime as i64,
+            None => true,
+        };
+
+        if !is_new_or_changed {
+            continue;
+        }
+
+        match process_and_store_file(pool, path, config).await {
+            Ok(has_workflow) => {
+                stats.total_processed += 1;
+                if has_workflow {
+                    stats.files_with_workflows += 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("Subpath sync failed on {}: {}", path.display(), e);
+                stats.failed_files += 1;
+            }
+        }
+    }
+
+    let stale_paths: Vec<String> = db_files.keys()
+        .filter(|path| !seen_paths.contains(*path))
+        .cloned()
+        .collect();
+    if !stale_paths.is_empty() {
+        database::delete_files_by_path(pool, &stale_paths).await?;
+    }
+
+    Ok(stats)
+}
+
+/// How many files `reconcile_files` stats per DB round-trip batch
+const RECONCILE_BATCH_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconcileStats {
+    pub checked: usize,
+    pub present: usize,
+    pub missing: usize,
+    pub modified: usize,
+    pub errors: usize,
+}
+
+/// Walk every indexed file in batches, `stat`ing its path and updating
+/// `status`/`checked_at`/`file_size`: `missing` when the path is gone,
+/// `modified` when the on-disk mtime or size no longer matches what's
+/// stored, `present` otherwise. This only updates the health columns — it
+/// never reprocesses metadata/workflow data — so a full library's status can
+/// be refreshed without paying the cost of `full_sync`.
+pub async fn reconcile_files(pool: &sqlx::SqlitePool) -> Result<ReconcileStats, String> {
+    let mut stats = ReconcileStats { checked: 0, present: 0, missing: 0, modified: 0, errors: 0 };
+    let mut after_id = String::new();
+
+    loop {
+        let batch = database::get_files_batch(pool, &after_id, RECONCILE_BATCH_SIZE).await?;
+        if batch.is_empty() {
+            break;
+        }
+
+        for row in &batch {
+            let path = PathBuf::from(&row.path);
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0);
+
+            let (status, file_size) = match std::fs::metadata(&path) {
+                Ok(meta) => {
+                    let disk_size = meta.len() as i64;
+                    let disk_mtime = get_mtime(&path).unwrap_or(0.0);
+                    let size_changed = row.file_size.map(|stored| stored != disk_size).unwrap_or(false);
+                    let mtime_changed = disk_mtime as i64 > row.mtime as i64;
+                    if size_changed || mtime_changed {
+                        ("modified", Some(disk_size))
+                    } else {
+                        ("present", Some(disk_size))
+                    }
+                }
+                Err(_) => ("missing", None),
+            };
+
+            if let Err(e) = database::update_file_status(pool, &row.id, status, now, file_size).await {
+                eprintln!("Failed to update status for {}: {}", row.path, e);
+                stats.errors += 1;
+                continue;
+            }
+
+            stats.checked += 1;
+            match status {
+                "present" => stats.present += 1,
+                "missing" => stats.missing += 1,
+                "modified" => stats.modified += 1,
+                _ => {}
+            }
+        }
+
+        after_id = batch.last().unwrap().id.clone();
+    }
+
+    Ok(stats)
+}
+
 /// Get file modification time as f64 (seconds since epoch)
 fn get_mtime(path: &Path) -> Result<f64, String> {
     let metadata = std::fs::metadata(path)
@@ -138,6 +662,13 @@ fn get_mtime(path: &Path) -> Result<f64, String> {
     Ok(duration.as_secs_f64())
 }
 
+/// Get file size in bytes
+fn get_size(path: &Path) -> Result<i64, String> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to get metadata: {}", e))?;
+    Ok(metadata.len() as i64)
+}
+
 /// Generate file ID from path (simple hash)
 fn generate_file_id(path: &Path) -> String {
     use sha2::{Sha256, Digest};
@@ -147,6 +678,30 @@ fn generate_file_id(path: &Path) -> String {
     hex::encode(&result[..16]) // Use first 16 bytes
 }
 
+/// Digest a file's content (not its path), so thumbnails/dedup keyed on this
+/// hash stay valid across moves and renames. Streamed in fixed-size chunks so
+/// large videos aren't loaded into memory wholesale.
+pub fn compute_content_hash(path: &Path) -> Result<String, String> {
+    use sha2::{Sha256, Digest};
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open file for hashing: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buf)
+            .map_err(|e| format!("Failed to read file for hashing: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
 /// Process a single file: extract metadata, workflow, create thumbnail
 /// Returns both the FileEntry and the parsed workflow data
 pub fn process_file(
@@ -185,10 +740,48 @@ pub fn process_file(
     
     // Extract workflow if present
     let (has_workflow, workflow_metadata) = extract_workflow_from_file(filepath)?;
-    
+
     // Get dimensions and duration based on file type
     let (dimensions, duration) = extract_media_metadata(filepath, file_type)?;
-    
+
+    // Optional integrity pass: catches truncated downloads / broken renders
+    // that a silent `None` from extract_media_metadata would otherwise hide
+    let (integrity_status, integrity_error) = if config.integrity_check {
+        let (status, error) = check_integrity(filepath, file_type);
+        (Some(status.as_str().to_string()), error)
+    } else {
+        (None, None)
+    };
+
+    let content_hash = compute_content_hash(filepath)?;
+
+    let file_entry = assemble_file_entry(
+        filepath, file_id, file_name, mtime, file_type,
+        has_workflow, &workflow_metadata, dimensions, duration,
+        integrity_status, integrity_error, &content_hash, config,
+    );
+
+    // Return both the file entry and the workflow metadata
+    Ok((file_entry, workflow_metadata))
+}
+
+/// Build the prompt preview/sampler summary, generate the thumbnail, and
+/// assemble the final `FileEntry` from already-extracted (or cached) metadata
+fn assemble_file_entry(
+    filepath: &Path,
+    file_id: String,
+    file_name: String,
+    mtime: f64,
+    file_type: &str,
+    has_workflow: bool,
+    workflow_metadata: &[parser::ParsedWorkflow],
+    dimensions: Option<String>,
+    duration: Option<String>,
+    integrity_status: Option<String>,
+    integrity_error: Option<String>,
+    content_hash: &str,
+    config: &ScannerConfig,
+) -> FileEntry {
     // Calculate prompt preview and sampler names from workflow metadata
     let (prompt_preview, sampler_names, sampler_count) = if !workflow_metadata.is_empty() {
         let first_prompt = workflow_metadata[0].positive_prompt.clone();
@@ -212,8 +805,27 @@ pub fn process_file(
     } else {
         (None, None, 0)
     };
-    
-    let file_entry = FileEntry {
+
+    // Generate (or reuse) a thumbnail; a failure here shouldn't fail the whole scan
+    let mut thumbnail_config = ThumbnailConfig::new(config.thumbnail_cache_dir.clone());
+    thumbnail_config.width = config.thumbnail_width;
+    thumbnail_config.height = config.thumbnail_width * 2;
+    let thumbnail_path = match thumbnails::generate_scan_thumbnail(
+        filepath,
+        content_hash,
+        file_type,
+        mtime,
+        &config.animated_extensions,
+        &thumbnail_config,
+    ) {
+        Ok(path) => Some(path.to_string_lossy().to_string()),
+        Err(e) => {
+            eprintln!("Thumbnail generation failed for {}: {}", filepath.display(), e);
+            None
+        }
+    };
+
+    FileEntry {
         id: file_id,
         path: filepath.to_string_lossy().to_string(),
         name: file_name,
@@ -226,21 +838,36 @@ pub fn process_file(
         dimensions,
         duration,
         sampler_count,
-    };
-    
-    // Return both the file entry and the workflow metadata
-    Ok((file_entry, workflow_metadata))
+        thumbnail_path,
+        integrity_status,
+        integrity_error,
+        content_hash: Some(content_hash.to_string()),
+        status: Some("present".to_string()),
+        checked_at: Some(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0),
+        ),
+        file_size: get_size(filepath).ok(),
+    }
 }
 
-/// Extract workflow from PNG tEXt chunk or video metadata
+const CONTAINER_EXTENSIONS: &[&str] = &[
+    "mp4", "avi", "mov", "mkv", "webm", "flv",
+    "mp3", "wav", "ogg", "flac", "m4a",
+];
+
+/// Extract workflow from PNG tEXt chunk, or from container-level metadata for video/audio
 fn extract_workflow_from_file(filepath: &Path) -> Result<(bool, Vec<parser::ParsedWorkflow>), String> {
     // Try to read file and extract workflow JSON
     let extension = filepath.extension()
         .and_then(|e| e.to_str())
-        .unwrap_or("");
-    
-    if extension.to_lowercase() == "png" {
-        // Extract from PNG tEXt chunk
+        .unwrap_or("")
+        .to_lowercase();
+
+    if extension == "png" {
+        // Extract from PNG tEXt/zTXt/iTXt chunk
         match extract_png_workflow(filepath) {
             Ok(Some(workflow_json)) => {
                 match parser::extract_workflow_metadata(&workflow_json, filepath) {
@@ -252,78 +879,256 @@ fn extract_workflow_from_file(filepath: &Path) -> Result<(bool, Vec<parser::Pars
             Ok(None) => Ok((false, Vec::new())),
             Err(_) => Ok((false, Vec::new())),
         }
+    } else if extension == "jpg" || extension == "jpeg" || extension == "webp" {
+        // Extract from EXIF UserComment/ImageDescription (JPEG) or the EXIF/XMP
+        // chunks of a WebP container
+        match extract_exif_workflow(filepath) {
+            Ok(Some(workflow_json)) => {
+                match parser::extract_workflow_metadata(&workflow_json, filepath) {
+                    Ok(metadata) if !metadata.is_empty() => Ok((true, metadata)),
+                    Ok(_) => Ok((true, Vec::new())),
+                    Err(_) => Ok((true, Vec::new())),
+                }
+            }
+            Ok(None) => Ok((false, Vec::new())),
+            Err(_) => Ok((false, Vec::new())),
+        }
+    } else if CONTAINER_EXTENSIONS.contains(&extension.as_str()) {
+        // ComfyUI's VHS/SaveVideo nodes stash the workflow JSON in a container-level
+        // comment/workflow/prompt tag (MP4 udta/moov, WebM/Matroska TAGS elements)
+        match extract_container_workflow(filepath) {
+            Ok(Some(workflow_json)) => {
+                match parser::extract_workflow_metadata(&workflow_json, filepath) {
+                    Ok(metadata) if !metadata.is_empty() => Ok((true, metadata)),
+                    Ok(_) => Ok((true, Vec::new())),
+                    Err(_) => Ok((true, Vec::new())),
+                }
+            }
+            Ok(None) => Ok((false, Vec::new())),
+            Err(_) => Ok((false, Vec::new())),
+        }
     } else {
-        // For videos and other formats, we'd need to implement metadata extraction
-        // For now, return no workflow
         Ok((false, Vec::new()))
     }
 }
 
-/// Extract workflow JSON from PNG tEXt chunk
+/// Run `ffprobe` and parse its JSON report of format/stream metadata
+fn run_ffprobe(filepath: &Path) -> Result<Value, String> {
+    let output = std::process::Command::new("ffprobe")
+        .arg("-v").arg("quiet")
+        .arg("-print_format").arg("json")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg(filepath)
+        .output()
+        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", stderr));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))
+}
+
+/// Extract workflow JSON stashed in container-level tags by ffprobe's `format.tags`
+fn extract_container_workflow(filepath: &Path) -> Result<Option<String>, String> {
+    let probe = run_ffprobe(filepath)?;
+
+    let tags = probe.get("format")
+        .and_then(|f| f.get("tags"))
+        .and_then(|t| t.as_object());
+
+    let Some(tags) = tags else {
+        return Ok(None);
+    };
+
+    for key in ["workflow", "WORKFLOW", "prompt", "PROMPT", "comment", "Comment", "COMMENT"] {
+        if let Some(value) = tags.get(key).and_then(|v| v.as_str()) {
+            // Only treat it as a workflow if it parses as JSON (plain comments don't)
+            if serde_json::from_str::<Value>(value).is_ok() {
+                return Ok(Some(value.to_string()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// True if `keyword` looks like a ComfyUI metadata field worth inflating/decoding
+fn is_workflow_keyword(keyword: &str) -> bool {
+    let keyword = keyword.to_lowercase();
+    keyword.contains("workflow") || keyword.contains("prompt")
+}
+
+/// Extract workflow JSON from a PNG `tEXt`, `zTXt`, or `iTXt` chunk
 fn extract_png_workflow(filepath: &Path) -> Result<Option<String>, String> {
     use std::fs::File;
     use std::io::{Read, BufReader};
-    
+    use flate2::read::ZlibDecoder;
+
     let file = File::open(filepath)
         .map_err(|e| format!("Failed to open file: {}", e))?;
     let mut reader = BufReader::new(file);
-    
+
     // Read PNG signature
     let mut sig = [0u8; 8];
     reader.read_exact(&mut sig)
         .map_err(|e| format!("Failed to read PNG signature: {}", e))?;
-    
+
     if sig != [137, 80, 78, 71, 13, 10, 26, 10] {
         return Ok(None); // Not a PNG
     }
-    
-    // Read chunks looking for tEXt with "workflow" or "prompt"
+
+    // Read chunks looking for tEXt/zTXt/iTXt with "workflow" or "prompt"
     loop {
         let mut length_buf = [0u8; 4];
         if reader.read_exact(&mut length_buf).is_err() {
             break;
         }
         let length = u32::from_be_bytes(length_buf);
-        
+
         let mut type_buf = [0u8; 4];
         if reader.read_exact(&mut type_buf).is_err() {
             break;
         }
-        
-        // Check if this is a tEXt chunk
-        if &type_buf == b"tEXt" {
-            let mut data = vec![0u8; length as usize];
-            if reader.read_exact(&mut data).is_err() {
-                break;
+
+        let mut data = vec![0u8; length as usize];
+        if reader.read_exact(&mut data).is_err() {
+            break;
+        }
+        // Skip CRC
+        let mut crc = [0u8; 4];
+        if reader.read_exact(&mut crc).is_err() {
+            break;
+        }
+
+        let text = match &type_buf {
+            b"tEXt" => {
+                // keyword \0 text (uncompressed, Latin-1)
+                data.iter().position(|&b| b == 0).and_then(|null_pos| {
+                    let keyword = String::from_utf8_lossy(&data[..null_pos]).to_string();
+                    is_workflow_keyword(&keyword)
+                        .then(|| String::from_utf8_lossy(&data[null_pos + 1..]).to_string())
+                })
             }
-            
-            // Find null separator
-            if let Some(null_pos) = data.iter().position(|&b| b == 0) {
-                let keyword = String::from_utf8_lossy(&data[..null_pos]);
-                let text = String::from_utf8_lossy(&data[null_pos + 1..]);
-                
-                // Check for workflow keywords
-                if keyword.to_lowercase().contains("workflow") 
-                    || keyword.to_lowercase().contains("prompt") {
-                    return Ok(Some(text.to_string()));
-                }
+            b"zTXt" => {
+                // keyword \0 compression-method(1) zlib-compressed-text
+                data.iter().position(|&b| b == 0).and_then(|null_pos| {
+                    let keyword = String::from_utf8_lossy(&data[..null_pos]).to_string();
+                    if !is_workflow_keyword(&keyword) || data.len() <= null_pos + 1 {
+                        return None;
+                    }
+                    let compressed = &data[null_pos + 2..];
+                    let mut decoder = ZlibDecoder::new(compressed);
+                    let mut out = String::new();
+                    decoder.read_to_string(&mut out).ok().map(|_| out)
+                })
             }
-            
-            // Skip CRC
-            let mut crc = [0u8; 4];
-            let _ = reader.read_exact(&mut crc);
-        } else {
-            // Skip chunk data and CRC
-            let mut skip_buf = vec![0u8; (length + 4) as usize];
-            if reader.read_exact(&mut skip_buf).is_err() {
-                break;
+            b"iTXt" => {
+                // keyword \0 compression-flag(1) compression-method(1) lang-tag \0 translated-keyword \0 text
+                let mut parts = data.splitn(4, |&b| b == 0);
+                let keyword = parts.next().map(|k| String::from_utf8_lossy(k).to_string());
+                let rest_after_keyword = data.iter().position(|&b| b == 0).map(|p| &data[p + 1..]);
+
+                keyword.zip(rest_after_keyword).and_then(|(keyword, rest)| {
+                    if !is_workflow_keyword(&keyword) || rest.len() < 2 {
+                        return None;
+                    }
+                    let compressed_flag = rest[0];
+                    let after_flags = &rest[2..];
+
+                    let lang_end = after_flags.iter().position(|&b| b == 0)?;
+                    let after_lang = &after_flags[lang_end + 1..];
+                    let keyword_end = after_lang.iter().position(|&b| b == 0)?;
+                    let text_bytes = &after_lang[keyword_end + 1..];
+
+                    if compressed_flag == 0 {
+                        Some(String::from_utf8_lossy(text_bytes).to_string())
+                    } else {
+                        let mut decoder = ZlibDecoder::new(text_bytes);
+                        let mut out = String::new();
+                        decoder.read_to_string(&mut out).ok().map(|_| out)
+                    }
+                })
             }
+            _ => None,
+        };
+
+        if let Some(text) = text {
+            return Ok(Some(text));
         }
     }
-    
+
     Ok(None)
 }
 
+/// Extract workflow JSON from a JPEG's EXIF `UserComment`/`ImageDescription`
+/// tag, or a WebP's `EXIF`/`XMP ` RIFF chunk
+fn extract_exif_workflow(filepath: &Path) -> Result<Option<String>, String> {
+    let bytes = std::fs::read(filepath)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    if bytes.starts_with(b"RIFF") && bytes.len() > 12 && &bytes[8..12] == b"WEBP" {
+        return Ok(find_workflow_json_in_webp_chunks(&bytes));
+    }
+
+    // JPEG: read EXIF via the `exif` crate, then scan UserComment/ImageDescription
+    let mut cursor = std::io::Cursor::new(&bytes);
+    let exif_reader = exif::Reader::new();
+    let Ok(exif_data) = exif_reader.read_from_container(&mut cursor) else {
+        return Ok(None);
+    };
+
+    for tag in [exif::Tag::UserComment, exif::Tag::ImageDescription] {
+        if let Some(field) = exif_data.get_field(tag, exif::In::PRIMARY) {
+            let text = field.display_value().to_string();
+            if serde_json::from_str::<Value>(&text).is_ok() {
+                return Ok(Some(text));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Scan a WebP's top-level RIFF chunks for an `EXIF` or `XMP ` chunk carrying
+/// the embedded workflow JSON
+fn find_workflow_json_in_webp_chunks(bytes: &[u8]) -> Option<String> {
+    let mut offset = 12; // past "RIFF" + size + "WEBP"
+
+    while offset + 8 <= bytes.len() {
+        let chunk_id = &bytes[offset..offset + 4];
+        let chunk_size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().ok()?) as usize;
+        let data_start = offset + 8;
+        let data_end = data_start.checked_add(chunk_size)?;
+        if data_end > bytes.len() {
+            break;
+        }
+        let data = &bytes[data_start..data_end];
+
+        if chunk_id == b"EXIF" || chunk_id == b"XMP " {
+            let text = String::from_utf8_lossy(data);
+            // The payload may be wrapped in EXIF TIFF headers or XMP packet
+            // framing; fall back to scanning for the first JSON object inside it
+            if let Some(start) = text.find('{') {
+                if let Some(end) = text.rfind('}') {
+                    let candidate = &text[start..=end];
+                    if serde_json::from_str::<Value>(candidate).is_ok() {
+                        return Some(candidate.to_string());
+                    }
+                }
+            }
+        }
+
+        // Chunks are padded to an even byte boundary
+        offset = data_end + (chunk_size % 2);
+    }
+
+    None
+}
+
 /// Extract media metadata (dimensions, duration)
 fn extract_media_metadata(filepath: &Path, file_type: &str) -> Result<(Option<String>, Option<String>), String> {
     if file_type == "image" {
@@ -332,41 +1137,74 @@ fn extract_media_metadata(filepath: &Path, file_type: &str) -> Result<(Option<St
             let (width, height) = (img.width(), img.height());
             return Ok((Some(format!("{}x{}", width, height)), None));
         }
+        return Ok((None, None));
+    }
+
+    if file_type == "video" || file_type == "audio" {
+        return Ok(extract_av_metadata(filepath));
     }
-    // For video/audio, we'd need ffprobe or similar - return None for now
+
     Ok((None, None))
 }
 
-/// Full database sync: compare disk files with database and process changes
-pub async fn full_sync(
+/// Pull dimensions/duration for video and audio files via `ffprobe`
+fn extract_av_metadata(filepath: &Path) -> (Option<String>, Option<String>) {
+    let probe = match run_ffprobe(filepath) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("ffprobe metadata extraction failed for {}: {}", filepath.display(), e);
+            return (None, None);
+        }
+    };
+
+    let dimensions = probe.get("streams")
+        .and_then(|s| s.as_array())
+        .and_then(|streams| {
+            streams.iter().find(|s| {
+                s.get("codec_type").and_then(|t| t.as_str()) == Some("video")
+            })
+        })
+        .and_then(|video_stream| {
+            let width = video_stream.get("width").and_then(|w| w.as_i64())?;
+            let height = video_stream.get("height").and_then(|h| h.as_i64())?;
+            Some(format!("{}x{}", width, height))
+        });
+
+    let duration = probe.get("format")
+        .and_then(|f| f.get("duration"))
+        .and_then(|d| d.as_str())
+        .and_then(|s| s.parse::<f64>().ok())
+        .map(|secs| format!("{:.2}", secs));
+
+    (dimensions, duration)
+}
+
+/// Compare disk files with the database and build the list of paths that need
+/// (re)processing: new files plus files whose on-disk mtime is newer than what's
+/// recorded. Shared by both the one-shot `full_sync` and the resumable job
+/// subsystem in `jobs.rs` so they always agree on what work there is to do.
+pub async fn compute_work_list(
     pool: &sqlx::SqlitePool,
     config: &ScannerConfig,
-    progress_callback: Option<Box<dyn Fn(SyncProgress) + Send + Sync>>,
-) -> Result<ScanStats, String> {
-    // Get all files from database
+) -> Result<Vec<String>, String> {
     let db_files_vec = database::get_all_file_paths(pool).await?;
     let db_files: HashMap<String, f64> = db_files_vec.into_iter().collect();
-    
-    // Scan disk for files
+
     let disk_files_paths = scan_directory(config)?;
-    
-    // Build disk files map with mtimes
+
     let mut disk_files: HashMap<String, f64> = HashMap::new();
     for path in &disk_files_paths {
         if let Ok(mtime) = get_mtime(path) {
             disk_files.insert(path.to_string_lossy().to_string(), mtime);
         }
     }
-    
-    // Determine what to add, update, delete
+
     let db_paths: HashSet<String> = db_files.keys().cloned().collect();
     let disk_paths: HashSet<String> = disk_files.keys().cloned().collect();
-    
-    let to_delete: Vec<String> = db_paths.difference(&disk_paths).cloned().collect();
+
     let to_add: Vec<String> = disk_paths.difference(&db_paths).cloned().collect();
     let to_check: Vec<String> = disk_paths.intersection(&db_paths).cloned().collect();
-    
-    // Find files that need updating (mtime changed)
+
     let to_update: Vec<String> = to_check
         .into_iter()
         .filter(|path| {
@@ -375,111 +1213,155 @@ pub async fn full_sync(
             disk_mtime > db_mtime
         })
         .collect();
-    
-    // Combine files to process
+
     let mut files_to_process: Vec<String> = to_add;
     files_to_process.extend(to_update);
-    
-    let total_files = files_to_process.len();
-    
-    // Delete removed files
-    for _path in &to_delete {
-        // We'd need to get the file_id from the path first
-        // For now, just note that we'd delete them
-    }
-    
-    // Process files in parallel using Rayon
-    let stats = Arc::new(Mutex::new(ScanStats::new()));
-    let processed = Arc::new(Mutex::new(0usize));
-    let pool_arc = Arc::new(pool.clone());
-    
-    files_to_process
-        .par_iter()
-        .for_each(|path| {
-            let path_buf = PathBuf::from(path);
-            let pool_clone = pool_arc.clone();
-            
-            // Use block_on to execute async database operations in sync context
-            let result = tauri::async_runtime::block_on(async {
-                // Process file to get metadata AND workflow data in one pass
-                let (mut file_entry, workflow_metadata) = match process_file(&path_buf, config) {
-                    Ok(data) => data,
-                    Err(e) => {
-                        eprintln!("Failed to process file {}: {}", path, e);
-                        return Err(e);
-                    }
-                };
-                
-                // Preserve favorite status if file already exists in database
-                if let Ok(Some(existing_file)) = database::get_file_by_id(&pool_clone, &file_entry.id).await {
-                    file_entry.is_favorite = existing_file.is_favorite;
-                }
 
-                // **CRITICAL FIX: Save file entry to database**
-                if let Err(e) = database::upsert_file(&pool_clone, &file_entry).await {
-                    eprintln!("Failed to upsert file record for {}: {}", path, e);
-                    return Err(format!("Database error: {}", e));
-                }
+    Ok(files_to_process)
+}
 
-                // **Save workflow metadata if present**
-                if !workflow_metadata.is_empty() {
-                    for (i, parsed) in workflow_metadata.iter().enumerate() {
-                        let meta = crate::models::WorkflowMetadata {
-                            id: None,
-                            file_id: file_entry.id.clone(),
-                            sampler_index: i as i32,
-                            model_name: parsed.model_name.clone(),
-                            sampler_name: parsed.sampler_name.clone(),
-                            scheduler: parsed.scheduler.clone(),
-                            cfg: parsed.cfg,
-                            steps: parsed.steps,
-                            positive_prompt: Some(parsed.positive_prompt.clone()),
-                            negative_prompt: Some(parsed.negative_prompt.clone()),
-                            width: parsed.width,
-                            height: parsed.height,
-                        };
-                        
-                        if let Err(e) = database::insert_workflow_metadata(&pool_clone, &meta).await {
-                            eprintln!("Failed to insert workflow metadata for {}: {}", path, e);
-                        }
-                    }
-                }
-                
-                Ok((file_entry.has_workflow, workflow_metadata.len()))
-            });
-            
-            // Update stats based on result
-            match result {
-                Ok((has_workflow, metadata_count)) => {
-                    let mut stats_guard = stats.lock().unwrap();
-                    stats_guard.total_processed += 1;
-                    if has_workflow {
-                        stats_guard.files_with_workflows += 1;
-                        if metadata_count > 0 {
-                            stats_guard.metadata_extracted += metadata_count;
-                        }
-                    }
-                }
-                Err(_) => {
-                    let mut stats_guard = stats.lock().unwrap();
-                    stats_guard.failed_files += 1;
-                }
-            }
-            
-            // Update progress
-            let mut processed_guard = processed.lock().unwrap();
-            *processed_guard += 1;
-            
-            if let Some(ref callback) = progress_callback {
-                callback(SyncProgress {
-                    status: "processing".to_string(),
-                    current: *processed_guard,
-                    total: total_files,
-                    message: Some(format!("Processing {}/{}", *processed_guard, total_files)),
-                });
+/// Process a file, consulting the `scan_cache` table first: if the file's
+/// `(path, mtime, size)` triple matches a cached entry, the expensive PNG
+/// chunk / ffprobe extraction in `process_file` is skipped entirely and the
+/// cached dimensions/duration/workflow metadata are reused. Cache misses fall
+/// through to `process_file` and populate the cache for next time.
+pub async fn process_file_cached(
+    pool: &sqlx::SqlitePool,
+    filepath: &Path,
+    config: &ScannerConfig,
+) -> Result<(FileEntry, Vec<parser::ParsedWorkflow>), String> {
+    let path_str = filepath.to_string_lossy().to_string();
+    let mtime = get_mtime(filepath)?;
+    let size = get_size(filepath)?;
+
+    if let Some(cached) = database::get_scan_cache_entry(pool, &path_str, mtime, size).await? {
+        let file_id = generate_file_id(filepath);
+        let file_name = filepath.file_name()
+            .and_then(|n| n.to_str())
+            .ok_or("Invalid filename")?
+            .to_string();
+        let extension = filepath.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let ext_lower = format!(".{}", extension.to_lowercase());
+        let file_type = if config.image_extensions.contains(&ext_lower) {
+            "image"
+        } else if config.video_extensions.contains(&ext_lower) {
+            "video"
+        } else if config.audio_extensions.contains(&ext_lower) {
+            "audio"
+        } else {
+            "unknown"
+        };
+
+        // Cache rows written before content hashing existed don't have one yet;
+        // compute it once here and backfill the cache instead of rehashing on
+        // every future sync
+        let content_hash = match cached.content_hash {
+            Some(hash) => hash,
+            None => {
+                let hash = compute_content_hash(filepath)?;
+                database::upsert_scan_cache_entry(
+                    pool, &path_str, mtime, size,
+                    &cached.dimensions, &cached.duration,
+                    cached.has_workflow, &cached.workflow_metadata,
+                    &cached.integrity_status, &cached.integrity_error,
+                    &hash,
+                ).await?;
+                hash
             }
-        });
-    
-    let final_stats = stats.lock().unwrap().clone();
-    Ok(final_stats)
+        };
+
+        let file_entry = assemble_file_entry(
+            filepath, file_id, file_name, mtime, file_type,
+            cached.has_workflow, &cached.workflow_metadata,
+            cached.dimensions, cached.duration,
+            cached.integrity_status, cached.integrity_error, &content_hash, config,
+        );
+
+        return Ok((file_entry, cached.workflow_metadata));
+    }
+
+    let (file_entry, workflow_metadata) = process_file(filepath, config)?;
+
+    let content_hash = file_entry.content_hash.clone().unwrap_or_default();
+    database::upsert_scan_cache_entry(
+        pool, &path_str, mtime, size,
+        &file_entry.dimensions, &file_entry.duration,
+        file_entry.has_workflow, &workflow_metadata,
+        &file_entry.integrity_status, &file_entry.integrity_error,
+        &content_hash,
+    ).await?;
+
+    Ok((file_entry, workflow_metadata))
+}
+
+/// Process a single file and persist its file entry + workflow metadata to the
+/// database. Returns whether the file had an embedded workflow. This is the
+/// sequential, single-file counterpart to the per-item work done inside
+/// `full_sync`'s worker threads (see `parallel_scan_and_store`), reused by the resumable job subsystem which
+/// needs to check a cancellation/pause flag between individual files.
+pub async fn process_and_store_file(
+    pool: &sqlx::SqlitePool,
+    path: &Path,
+    config: &ScannerConfig,
+) -> Result<bool, String> {
+    let (mut file_entry, workflow_metadata) = process_file_cached(pool, path, config).await?;
+
+    if let Ok(Some(existing_file)) = database::get_file_by_id(pool, &file_entry.id).await {
+        file_entry.is_favorite = existing_file.is_favorite;
+        file_entry.status = existing_file.status;
+        file_entry.checked_at = existing_file.checked_at;
+        file_entry.file_size = existing_file.file_size;
+        if existing_file.content_hash != file_entry.content_hash {
+            database::delete_thumbnail_variants(pool, &file_entry.id).await
+                .map_err(|e| format!("Database error: {}", e))?;
+        }
+    }
+
+    database::upsert_file(pool, &file_entry).await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    for (i, parsed) in workflow_metadata.iter().enumerate() {
+        let meta = crate::models::WorkflowMetadata {
+            id: None,
+            file_id: file_entry.id.clone(),
+            sampler_index: i as i32,
+            model_name: parsed.model_name.clone(),
+            sampler_name: parsed.sampler_name.clone(),
+            scheduler: parsed.scheduler.clone(),
+            cfg: parsed.cfg,
+            steps: parsed.steps,
+            positive_prompt: Some(parsed.positive_prompt.clone()),
+            negative_prompt: Some(parsed.negative_prompt.clone()),
+            width: parsed.width,
+            height: parsed.height,
+            seed: parsed.seed,
+            denoise: parsed.denoise,
+            lora_names: lora_names(&parsed.loras),
+        };
+
+        if let Err(e) = database::insert_workflow_metadata(pool, &meta).await {
+            eprintln!("Failed to insert workflow metadata for {}: {}", path.display(), e);
+        }
+    }
+
+    Ok(file_entry.has_workflow)
+}
+
+/// Full database sync: walk the output directory and process new/changed
+/// files. The walk itself runs on a bounded pool of `WALK_WORKER_COUNT`
+/// worker threads that share the top-level subtrees between them, so large libraries don't pay for a single-threaded
+/// directory traversal; each worker's discovered files feed a single writer
+/// thread that commits them in batches. See `parallel_scan_and_store`.
+pub async fn full_sync(
+    pool: &sqlx::SqlitePool,
+    config: &ScannerConfig,
+    progress_callback: Option<Box<dyn Fn(SyncProgress) + Send + Sync>>,
+) -> Result<ScanStats, String> {
+    let db_mtimes: HashMap<String, f64> = database::get_all_file_paths(pool).await?
+        .into_iter()
+        .collect();
+
+    let stats = parallel_scan_and_store(pool.clone(), config.clone(), db_mtimes, progress_callback);
+
+    Ok(stats)
 }
@@ -0,0 +1,160 @@
+// Background thumbnail generation for SmartGallery
+//
+// `get_thumbnail_path` used to generate thumbnails one at a time, synchronously,
+// on first request — scrolling a fresh gallery stalled badly. This module moves
+// that work onto a small pool of background workers pulling from a bounded
+// priority queue: the frontend marks currently-visible files as `High` priority
+// via `request_thumbnails` so they jump ahead of background pre-generation, and
+// each completed thumbnail fires a `thumbnail-ready` event so the UI can swap
+// its placeholder in. There's no separate cancellation flag — a queued-but-not-started
+// `Low` entry is simply dropped (to make room once the queue is full) or bumped to
+// `High` (on a repeat request), so a user scrolling away costs nothing beyond
+// whatever file a worker already started on.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::models::FileEntry;
+use crate::thumbnails::{self, ThumbnailConfig};
+
+/// Files currently visible in the UI are requested at `High` priority and jump
+/// ahead of `Low` (background pre-generation) work already queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Low,
+    High,
+}
+
+/// Thumbnail encoding is cheap enough (and partly blocked on ffmpeg/disk) that
+/// a couple of workers keeps the visible-first queue moving without the pool
+/// contending heavily with the rest of the app
+const WORKER_COUNT: usize = 2;
+
+/// Cap on queued-but-not-started requests. Once full, the oldest `Low`
+/// priority entry is dropped to make room rather than growing unbounded while
+/// the user scrolls through a large gallery.
+const MAX_QUEUE_LEN: usize = 500;
+
+struct QueueState {
+    high: VecDeque<FileEntry>,
+    low: VecDeque<FileEntry>,
+    /// Which queue (if any) each file id currently sits in, so a repeat
+    /// request can re-prioritize in place instead of double-queuing
+    member: HashMap<String, Priority>,
+}
+
+impl QueueState {
+    fn len(&self) -> usize {
+        self.high.len() + self.low.len()
+    }
+
+    fn pop(&mut self) -> Option<FileEntry> {
+        let file = self.high.pop_front().or_else(|| self.low.pop_front())?;
+        self.member.remove(&file.id);
+        Some(file)
+    }
+}
+
+/// Handle shared between the Tauri command layer and the worker threads
+#[derive(Clone)]
+pub struct ThumbnailQueue {
+    state: Arc<Mutex<QueueState>>,
+    not_empty: Arc<Condvar>,
+    config: ThumbnailConfig,
+    animated_extensions: Vec<String>,
+}
+
+impl ThumbnailQueue {
+    /// Spawn the worker pool and return a handle for submitting requests
+    pub fn start(config: ThumbnailConfig, animated_extensions: Vec<String>, app_handle: AppHandle) -> Self {
+        let queue = Self {
+            state: Arc::new(Mutex::new(QueueState {
+                high: VecDeque::new(),
+                low: VecDeque::new(),
+                member: HashMap::new(),
+            })),
+            not_empty: Arc::new(Condvar::new()),
+            config,
+            animated_extensions,
+        };
+
+        for _ in 0..WORKER_COUNT {
+            let worker = queue.clone();
+            let app_handle = app_handle.clone();
+            std::thread::spawn(move || worker.run(app_handle));
+        }
+
+        queue
+    }
+
+    /// Enqueue (or re-prioritize) thumbnail requests for the given files
+    pub fn request(&self, files: Vec<FileEntry>, priority: Priority) {
+        let mut state = self.state.lock().unwrap();
+
+        for file in files {
+            match state.member.get(&file.id).copied() {
+                Some(current) if current == priority => continue,
+                Some(_) => {
+                    state.high.retain(|f| f.id != file.id);
+                    state.low.retain(|f| f.id != file.id);
+                }
+                None => {
+                    if state.len() >= MAX_QUEUE_LEN {
+                        match state.low.pop_front() {
+                            Some(dropped) => { state.member.remove(&dropped.id); }
+                            // Queue is full of High priority work; nothing safe to drop
+                            None => continue,
+                        }
+                    }
+                }
+            }
+
+            state.member.insert(file.id.clone(), priority);
+            match priority {
+                Priority::High => state.high.push_back(file),
+                Priority::Low => state.low.push_back(file),
+            }
+        }
+
+        self.not_empty.notify_all();
+    }
+
+    fn run(&self, app_handle: AppHandle) {
+        loop {
+            let file = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if let Some(file) = state.pop() {
+                        break file;
+                    }
+                    state = self.not_empty.wait(state).unwrap();
+                }
+            };
+
+            let content_hash = file.content_hash.clone().unwrap_or_default();
+            let thumbnail_path = thumbnails::generate_scan_thumbnail(
+                &PathBuf::from(&file.path),
+                &content_hash,
+                &file.file_type,
+                file.mtime,
+                &self.animated_extensions,
+                &self.config,
+            ).ok().map(|p| p.to_string_lossy().to_string());
+
+            let _ = app_handle.emit("thumbnail-ready", &ThumbnailReady {
+                file_id: file.id,
+                thumbnail_path,
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ThumbnailReady {
+    file_id: String,
+    thumbnail_path: Option<String>,
+}
@@ -2,15 +2,75 @@
 // Creates thumbnails for images and videos
 
 use std::path::{Path, PathBuf};
-use image::{DynamicImage, imageops::FilterType, ImageFormat, GenericImageView};
+use image::{DynamicImage, imageops::FilterType, GenericImageView};
 use std::fs;
 
+/// Fixed cached resolutions for the multi-size thumbnail variant pipeline
+/// (see `get_or_create_variant`), smallest first
+pub const THUMBNAIL_SIZES: [u32; 3] = [256, 512, 1024];
+
+/// Preferred thumbnail encoding, in the default order of
+/// `ThumbnailConfig::formats`, which `get_or_create_auto_variant` tries: WebP for its size/quality balance, AVIF where the `image` crate's
+/// encoder is available for an even smaller file, JPEG as the universally
+/// supported fallback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailFormat {
+    Webp,
+    Avif,
+    Jpeg,
+}
+
+impl ThumbnailFormat {
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "avif" => ThumbnailFormat::Avif,
+            "jpeg" | "jpg" => ThumbnailFormat::Jpeg,
+            _ => ThumbnailFormat::Webp,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "webp",
+            ThumbnailFormat::Avif => "avif",
+            ThumbnailFormat::Jpeg => "jpeg",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Webp => "webp",
+            ThumbnailFormat::Avif => "avif",
+            ThumbnailFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Smallest cached tier in `THUMBNAIL_SIZES` that's still >= `requested`, or
+/// the largest tier if even that isn't enough — the gallery should never get
+/// back something smaller than it asked for
+pub fn nearest_size_tier(requested: u32) -> u32 {
+    THUMBNAIL_SIZES.iter()
+        .copied()
+        .find(|&size| size >= requested)
+        .unwrap_or(*THUMBNAIL_SIZES.last().unwrap())
+}
+
 #[derive(Debug, Clone)]
 pub struct ThumbnailConfig {
     pub cache_dir: PathBuf,
     pub width: u32,
     pub height: u32,
-    pub quality: u8,
+    /// Candidate encodings `get_or_create_auto_variant`'s smallest-wins
+    /// comparison tries per source image
+    pub formats: Vec<ThumbnailFormat>,
+    /// WebP encode quality (0-100), used for both still and video-frame thumbnails
+    pub webp_quality: u8,
+    /// AVIF encode quality (0-100); kept lower than WebP/JPEG by default since
+    /// AVIF's perceptual quality per bit is higher at the same numeric setting
+    pub avif_quality: u8,
+    /// JPEG encode quality (0-100), used for the fallback/universal format
+    pub jpeg_quality: u8,
 }
 
 impl ThumbnailConfig {
@@ -19,73 +79,22 @@ impl ThumbnailConfig {
             cache_dir,
             width: 200,
             height: 400, // 2x width for aspect ratio preservation
-            quality: 85,
+            formats: vec![ThumbnailFormat::Webp, ThumbnailFormat::Avif, ThumbnailFormat::Jpeg],
+            webp_quality: 85,
+            avif_quality: 60,
+            jpeg_quality: 85,
         }
     }
-}
-
-/// Generate file hash for thumbnail filename
-fn generate_file_hash(path: &Path) -> String {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(path.to_string_lossy().as_bytes());
-    let result = hasher.finalize();
-    hex::encode(&result[..16])
-}
 
-/// Create thumbnail for an image file
-pub fn create_image_thumbnail(
-    filepath: &Path,
-    config: &ThumbnailConfig,
-) -> Result<PathBuf, String> {
-    // Ensure cache directory exists
-    fs::create_dir_all(&config.cache_dir)
-        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
-    
-    // Generate hash for cache filename
-    let file_hash = generate_file_hash(filepath);
-    
-    // Load image
-    let img = image::open(filepath)
-        .map_err(|e| format!("Failed to open image: {}", e))?;
-    
-    // Determine output format based on input
-    let format = match filepath.extension().and_then(|e| e.to_str()) {
-        Some("gif") => ImageFormat::Gif,
-        Some("webp") => ImageFormat::WebP,
-        Some("png") => ImageFormat::Png,
-        _ => ImageFormat::Jpeg,
-    };
-    
-    let extension = match format {
-        ImageFormat::Gif => "gif",
-        ImageFormat::WebP => "webp",
-        ImageFormat::Png => "png",
-        _ => "jpeg",
-    };
-    
-    let cache_path = config.cache_dir.join(format!("{}.{}", file_hash, extension));
-    
-    // Check if thumbnail already exists
-    if cache_path.exists() {
-        return Ok(cache_path);
+    /// The configured quality for `format`, used wherever a single format is
+    /// being encoded rather than compared against the others
+    fn quality_for(&self, format: ThumbnailFormat) -> u8 {
+        match format {
+            ThumbnailFormat::Webp => self.webp_quality,
+            ThumbnailFormat::Avif => self.avif_quality,
+            ThumbnailFormat::Jpeg => self.jpeg_quality,
+        }
     }
-    
-    // Create thumbnail
-    let thumbnail = resize_image(&img, config.width, config.height);
-    
-    // Convert to RGB if needed
-    let thumbnail_rgb = if thumbnail.color().has_alpha() && format == ImageFormat::Jpeg {
-        DynamicImage::ImageRgb8(thumbnail.to_rgb8())
-    } else {
-        thumbnail
-    };
-    
-    // Save thumbnail
-    thumbnail_rgb.save_with_format(&cache_path, format)
-        .map_err(|e| format!("Failed to save thumbnail: {}", e))?;
-    
-    Ok(cache_path)
 }
 
 /// Resize image maintaining aspect ratio
@@ -100,140 +109,285 @@ fn resize_image(img: &DynamicImage, max_width: u32, max_height: u32) -> DynamicI
     img.resize(new_width, new_height, FilterType::Lanczos3)
 }
 
-/// Create thumbnail for a video file using ffmpeg
-pub fn create_video_thumbnail(
+/// Encode an image as WebP at `quality` (0-100) and write it to `path`. WebP
+/// gives noticeably smaller caches than the JPEG stills used previously, which
+/// matters once thumbnails are being pre-generated in bulk by the background queue.
+fn save_webp_thumbnail(img: &DynamicImage, path: &Path, quality: u8) -> Result<(), String> {
+    let rgba = img.to_rgba8();
+    let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+        .encode(quality as f32);
+    fs::write(path, &*encoded)
+        .map_err(|e| format!("Failed to write webp thumbnail: {}", e))
+}
+
+/// Encode `img` in `format` and write it to `path`, falling back to JPEG if
+/// the requested format's encoder isn't available for this image (e.g. an
+/// `image` build without the AVIF encoder feature) so a variant request never
+/// comes back empty-handed. Returns the format actually written.
+fn save_variant(img: &DynamicImage, path: &Path, format: ThumbnailFormat, quality: u8) -> Result<ThumbnailFormat, String> {
+    match format {
+        ThumbnailFormat::Webp => {
+            save_webp_thumbnail(img, path, quality)?;
+            Ok(ThumbnailFormat::Webp)
+        }
+        ThumbnailFormat::Avif => {
+            match img.save_with_format(path, image::ImageFormat::Avif) {
+                Ok(()) => Ok(ThumbnailFormat::Avif),
+                Err(e) => {
+                    eprintln!("AVIF encode unavailable ({}), falling back to JPEG", e);
+                    img.to_rgb8().save_with_format(path, image::ImageFormat::Jpeg)
+                        .map_err(|e| format!("Failed to write jpeg fallback thumbnail: {}", e))?;
+                    Ok(ThumbnailFormat::Jpeg)
+                }
+            }
+        }
+        ThumbnailFormat::Jpeg => {
+            img.to_rgb8().save_with_format(path, image::ImageFormat::Jpeg)
+                .map_err(|e| format!("Failed to write jpeg thumbnail: {}", e))?;
+            Ok(ThumbnailFormat::Jpeg)
+        }
+    }
+}
+
+/// Generate a single thumbnail variant at `size`/`format` for a scanned file
+/// (always encoding; cache lookup is the caller's job), named by
+/// `(content_hash, size, format)` so the cache survives moves/renames. For
+/// videos, the still frame is always written as WebP whatever `format` was
+/// requested; for images the requested format may silently become JPEG if
+/// the preferred encoder rejects the image (see `save_variant`) — callers
+/// should persist whatever format comes back, not the one they asked for.
+/// Returns `(absolute_path, format_written, bytes_written)`.
+pub fn get_or_create_variant(
     filepath: &Path,
+    content_hash: &str,
+    file_type: &str,
+    size: u32,
+    format: ThumbnailFormat,
     config: &ThumbnailConfig,
-) -> Result<PathBuf, String> {
-    // Ensure cache directory exists
+) -> Result<(PathBuf, ThumbnailFormat, u64), String> {
     fs::create_dir_all(&config.cache_dir)
         .map_err(|e| format!("Failed to create cache directory: {}", e))?;
-    
-    // Generate hash for cache filename
-    let file_hash = generate_file_hash(filepath);
-    let cache_path = config.cache_dir.join(format!("{}.jpeg", file_hash));
-    
-    // Check if thumbnail already exists
-    if cache_path.exists() {
-        return Ok(cache_path);
+
+    let mut out_path = config.cache_dir.join(format!("{}_{}.{}", content_hash, size, format.extension()));
+
+    let written_format = match file_type {
+        "image" => {
+            let img = image::open(filepath)
+                .map_err(|e| format!("Failed to open image: {}", e))?;
+            let thumb = resize_image(&img, size, size);
+            let written = save_variant(&thumb, &out_path, format, config.quality_for(format))?;
+            if written != format {
+                out_path = config.cache_dir.join(format!("{}_{}.{}", content_hash, size, written.extension()));
+                save_variant(&thumb, &out_path, written, config.quality_for(written))?;
+            }
+            written
+        }
+        "video" => {
+            // `extract_video_frame` drives ffmpeg's WebP muxer via `-quality`;
+            // video frames are always served as WebP regardless of the
+            // requested format, same rationale as `save_variant`'s fallback
+            out_path = config.cache_dir.join(format!("{}_{}.webp", content_hash, size));
+            extract_video_frame(filepath, &out_path, size, config.quality_for(ThumbnailFormat::Webp))?;
+            ThumbnailFormat::Webp
+        }
+        _ => return Err("Unsupported file type for thumbnail".to_string()),
+    };
+
+    let bytes = fs::metadata(&out_path)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to stat generated thumbnail: {}", e))?;
+
+    Ok((out_path, written_format, bytes))
+}
+
+/// Encode `size` in every one of `config.formats` and keep whichever produced
+/// the smallest file, so a caller that just wants "the best available format"
+/// (`preferred_format: "auto"`) doesn't have to guess WebP vs AVIF per source
+/// image — some images compress much better under one than the other. Losing
+/// candidates are deleted immediately rather than left to rot in the cache
+/// dir. Video frames skip the comparison (`extract_video_frame` only ever
+/// produces WebP) and fall straight through to `get_or_create_variant`.
+/// Returns `(absolute_path, format_written, bytes_written)`.
+pub fn get_or_create_auto_variant(
+    filepath: &Path,
+    content_hash: &str,
+    file_type: &str,
+    size: u32,
+    config: &ThumbnailConfig,
+) -> Result<(PathBuf, ThumbnailFormat, u64), String> {
+    if file_type != "image" {
+        return get_or_create_variant(filepath, content_hash, file_type, size, ThumbnailFormat::Webp, config);
     }
-    
-    // Use ffmpeg to extract a frame
-    let output = std::process::Command::new("ffmpeg")
-        .arg("-i")
-        .arg(filepath)
-        .arg("-ss")
-        .arg("00:00:01") // Seek to 1 second
-        .arg("-vframes")
-        .arg("1") // Extract 1 frame
-        .arg("-vf")
-        .arg(format!("scale={}:-1", config.width)) // Scale to thumbnail width
-        .arg("-q:v")
-        .arg("2") // Quality
-        .arg(&cache_path)
-        .arg("-y") // Overwrite
-        .output()
-        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
-    
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffmpeg failed: {}", stderr));
+
+    fs::create_dir_all(&config.cache_dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let img = image::open(filepath)
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    let thumb = resize_image(&img, size, size);
+
+    let mut best: Option<(PathBuf, ThumbnailFormat, u64)> = None;
+    for &format in &config.formats {
+        let mut candidate_path = config.cache_dir.join(format!("{}_{}.{}", content_hash, size, format.extension()));
+        let written = save_variant(&thumb, &candidate_path, format, config.quality_for(format))?;
+        if written != format {
+            candidate_path = config.cache_dir.join(format!("{}_{}.{}", content_hash, size, written.extension()));
+            save_variant(&thumb, &candidate_path, written, config.quality_for(written))?;
+        }
+        let bytes = fs::metadata(&candidate_path)
+            .map(|m| m.len())
+            .map_err(|e| format!("Failed to stat generated thumbnail: {}", e))?;
+
+        match &best {
+            Some((_, _, best_bytes)) if bytes >= *best_bytes => {
+                let _ = fs::remove_file(&candidate_path);
+            }
+            _ => {
+                if let Some((old_path, _, _)) = best.replace((candidate_path, written, bytes)) {
+                    let _ = fs::remove_file(&old_path);
+                }
+            }
+        }
     }
-    
-    Ok(cache_path)
+
+    best.ok_or_else(|| "No thumbnail formats configured".to_string())
 }
 
-/// Get thumbnail path for a file (create if doesn't exist)
-pub fn get_or_create_thumbnail(
+/// Generate (or reuse) a thumbnail for a scanned file, keyed by `(content_hash,
+/// width)` rather than the file's path, so moving or renaming the source file
+/// doesn't orphan its thumbnail and an identical copy reuses the existing one.
+///
+/// For images this writes a single scaled WebP still. Video files get a
+/// representative still frame (first non-black frame, approximated here as
+/// the frame at ~10% of the duration); video files and `animated_extensions`
+/// (gif/webp) images additionally get a short looping motion preview as an
+/// animated WebP, so the gallery can show a moving preview alongside the
+/// static thumbnail.
+pub fn generate_scan_thumbnail(
     filepath: &Path,
+    content_hash: &str,
     file_type: &str,
+    mtime: f64,
+    animated_extensions: &[String],
     config: &ThumbnailConfig,
 ) -> Result<PathBuf, String> {
-    match file_type {
-        "image" | "animated_image" => create_image_thumbnail(filepath, config),
-        "video" => create_video_thumbnail(filepath, config),
-        _ => Err("Unsupported file type for thumbnail".to_string()),
+    fs::create_dir_all(&config.cache_dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let still_path = config.cache_dir.join(format!("{}_{}.webp", content_hash, config.width));
+    let motion_path = config.cache_dir.join(format!("{}_{}_motion.webp", content_hash, config.width));
+
+    if is_thumbnail_fresh(&still_path, mtime) {
+        return Ok(still_path);
     }
-}
 
-/// Check if thumbnail exists for a file
-pub fn thumbnail_exists(filepath: &Path, config: &ThumbnailConfig) -> bool {
-    let file_hash = generate_file_hash(filepath);
-    
-    // Check for common thumbnail formats
-    for ext in &["jpeg", "jpg", "png", "gif", "webp"] {
-        let cache_path = config.cache_dir.join(format!("{}.{}", file_hash, ext));
-        if cache_path.exists() {
-            return true;
+    let ext_lower = filepath.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| format!(".{}", e.to_lowercase()))
+        .unwrap_or_default();
+    let is_animated = animated_extensions.contains(&ext_lower);
+
+    match file_type {
+        "image" => {
+            let img = image::open(filepath)
+                .map_err(|e| format!("Failed to open image: {}", e))?;
+            let thumb = resize_image(&img, config.width, config.height);
+            save_webp_thumbnail(&thumb, &still_path, config.webp_quality)?;
+
+            if is_animated {
+                // Best-effort: a motion preview is a nice-to-have, not worth failing the scan over
+                let _ = render_motion_preview(filepath, &motion_path, config.width);
+            }
+        }
+        "video" => {
+            extract_video_frame(filepath, &still_path, config.width, config.webp_quality)?;
+            let _ = render_motion_preview(filepath, &motion_path, config.width);
         }
+        _ => return Err("Unsupported file type for thumbnail".to_string()),
     }
-    
-    false
+
+    Ok(still_path)
 }
 
-/// Get existing thumbnail path without creating
-pub fn get_thumbnail_path(filepath: &Path, config: &ThumbnailConfig) -> Option<PathBuf> {
-    let file_hash = generate_file_hash(filepath);
-    
-    // Check for common thumbnail formats
-    for ext in &["jpeg", "jpg", "png", "gif", "webp"] {
-        let cache_path = config.cache_dir.join(format!("{}.{}", file_hash, ext));
-        if cache_path.exists() {
-            return Some(cache_path);
-        }
+/// True when a cached thumbnail exists and is newer than the source file's mtime
+fn is_thumbnail_fresh(cache_path: &Path, source_mtime: f64) -> bool {
+    let Ok(meta) = fs::metadata(cache_path) else {
+        return false;
+    };
+    let Ok(modified) = meta.modified() else {
+        return false;
+    };
+    let Ok(cached_mtime) = modified.duration_since(std::time::UNIX_EPOCH) else {
+        return false;
+    };
+    cached_mtime.as_secs_f64() >= source_mtime
+}
+
+/// Probe a media file's duration in seconds via `ffprobe`
+fn probe_duration_seconds(filepath: &Path) -> Option<f64> {
+    let output = std::process::Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-show_entries").arg("format=duration")
+        .arg("-of").arg("default=noprint_wrappers=1:nokey=1")
+        .arg(filepath)
+        .output()
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout).trim().parse::<f64>().ok()
+}
+
+/// Extract a representative frame (~10% into the clip) from a video as a still thumbnail
+fn extract_video_frame(filepath: &Path, out_path: &Path, width: u32, quality: u8) -> Result<(), String> {
+    let seek = probe_duration_seconds(filepath).map(|d| d * 0.1).unwrap_or(1.0);
+
+    let output = std::process::Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-ss").arg(format!("{:.2}", seek))
+        .arg("-i").arg(filepath)
+        .arg("-vframes").arg("1")
+        .arg("-vf").arg(format!("scale={}:-1", width))
+        .arg("-quality").arg(quality.to_string())
+        .arg(out_path)
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg frame extraction failed: {}", stderr));
     }
-    
-    None
+
+    Ok(())
 }
 
-/// Clean up old/unused thumbnails
-pub fn cleanup_thumbnails(
-    valid_file_paths: &[PathBuf],
-    config: &ThumbnailConfig,
-) -> Result<usize, String> {
-    let mut removed_count = 0;
-    
-    // Build set of valid hashes
-    let valid_hashes: std::collections::HashSet<String> = valid_file_paths
-        .iter()
-        .map(|p| generate_file_hash(p))
-        .collect();
-    
-    // Read cache directory
-    let entries = fs::read_dir(&config.cache_dir)
-        .map_err(|e| format!("Failed to read cache directory: {}", e))?;
-    
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() {
-                // Extract hash from filename
-                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                    if !valid_hashes.contains(stem) {
-                        // Remove orphaned thumbnail
-                        if fs::remove_file(&path).is_ok() {
-                            removed_count += 1;
-                        }
-                    }
-                }
-            }
-        }
+/// Render a short (2s) looping animated WebP preview starting ~10% into the source,
+/// used as a "motion thumbnail" for animated images and videos
+fn render_motion_preview(filepath: &Path, out_path: &Path, width: u32) -> Result<(), String> {
+    let seek = probe_duration_seconds(filepath).map(|d| d * 0.1).unwrap_or(0.0);
+
+    let output = std::process::Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-ss").arg(format!("{:.2}", seek))
+        .arg("-i").arg(filepath)
+        .arg("-t").arg("2")
+        .arg("-vf").arg(format!("scale={}:-1", width))
+        .arg("-loop").arg("0")
+        .arg("-an")
+        .arg(out_path)
+        .output()
+        .map_err(|e| format!("Failed to execute ffmpeg: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg motion preview failed: {}", stderr));
     }
-    
-    Ok(removed_count)
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     
-    #[test]
-    fn test_generate_file_hash() {
-        let path = Path::new("/test/file.png");
-        let hash = generate_file_hash(path);
-        assert_eq!(hash.len(), 32); // 16 bytes hex encoded
-    }
-    
     #[test]
     fn test_resize_image() {
         // Create a simple test image
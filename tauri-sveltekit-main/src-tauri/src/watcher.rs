@@ -0,0 +1,106 @@
+// Filesystem watcher for SmartGallery
+//
+// Watches the output directory for changes so new/updated/removed ComfyUI
+// outputs get indexed without the user manually triggering a sync. Events are
+// debounced and coalesced by containing folder, then each affected folder is
+// re-indexed via `scanner::shallow_sync` rather than a full `full_sync` pass
+// over the whole tree.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use sqlx::SqlitePool;
+use tauri::Emitter;
+
+use crate::scanner::{self, ScannerConfig};
+
+/// Coalesce a burst of filesystem events (e.g. ComfyUI writing several
+/// outputs back to back) into a single re-index pass per folder
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Start watching `config.output_path` for changes on a background thread.
+/// Returns a stop flag the caller can set (see `commands::restore_snapshot`)
+/// to have the thread exit before its next re-index pass, rather than
+/// keeping it running against a database file that's about to be replaced.
+pub fn start(pool: SqlitePool, config: ScannerConfig, app_handle: tauri::AppHandle) -> Arc<AtomicBool> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = stop.clone();
+
+    std::thread::spawn(move || {
+        let (tx, rx) = channel();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Failed to start filesystem watcher: {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = watcher.watch(&config.output_path, RecursiveMode::Recursive) {
+            eprintln!("Failed to watch {}: {}", config.output_path.display(), e);
+            return;
+        }
+
+        loop {
+            if stop_flag.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // Poll with a timeout rather than a blocking `recv` so the loop
+            // can notice `stop_flag` even while idle between filesystem events
+            let first = match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let mut folders = HashSet::new();
+            collect_folders(first, &mut folders);
+
+            // Drain anything else that arrives during the debounce window so
+            // a burst of events triggers one re-index per folder, not one per file
+            std::thread::sleep(DEBOUNCE);
+            while let Ok(event) = rx.try_recv() {
+                collect_folders(event, &mut folders);
+            }
+
+            if stop_flag.load(Ordering::Relaxed) {
+                break;
+            }
+
+            for folder in folders {
+                let pool = pool.clone();
+                let config = config.clone();
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    match scanner::shallow_sync(&pool, &config, &folder).await {
+                        Ok(stats) => {
+                            let _ = app_handle.emit("fs-change", &stats);
+                        }
+                        Err(e) => {
+                            eprintln!("Watcher-triggered shallow sync failed for {}: {}", folder.display(), e);
+                        }
+                    }
+                });
+            }
+        }
+    });
+
+    stop
+}
+
+fn collect_folders(event: notify::Result<notify::Event>, folders: &mut HashSet<PathBuf>) {
+    let Ok(event) = event else {
+        return;
+    };
+    for path in event.paths {
+        if let Some(parent) = path.parent() {
+            folders.insert(parent.to_path_buf());
+        }
+    }
+}